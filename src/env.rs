@@ -0,0 +1,55 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Окружение вычисления: значения именованных переменных.
+///
+/// Переменные задаются присваиванием вида `x = 3*4` и доступны
+/// во всех последующих выражениях. Отдельно хранится результат
+/// предыдущего вычисления, который подставляется вместо `Ans`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Env {
+    vars: BTreeMap<String, f64>,
+    ans: Option<f64>,
+}
+
+impl Env {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Значение переменной, если она определена.
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.vars.get(name).copied()
+    }
+
+    /// Задать значение переменной.
+    pub fn set(&mut self, name: impl Into<String>, val: f64) {
+        self.vars.insert(name.into(), val);
+    }
+
+    /// Удалить переменную и вернуть ее значение.
+    pub fn remove(&mut self, name: &str) -> Option<f64> {
+        self.vars.remove(name)
+    }
+
+    /// Результат предыдущего вычисления.
+    pub fn ans(&self) -> Option<f64> {
+        self.ans
+    }
+
+    /// Запомнить результат вычисления.
+    pub fn set_ans(&mut self, val: f64) {
+        self.ans = Some(val);
+    }
+
+    /// Удалить все переменные. Предыдущий результат сохраняется.
+    pub fn clear(&mut self) {
+        self.vars.clear();
+    }
+
+    /// Переменные в алфавитном порядке.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.vars.iter().map(|(name, val)| (name.as_str(), *val))
+    }
+}
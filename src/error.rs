@@ -0,0 +1,121 @@
+use crate::i18n;
+
+/// Ошибки вычисления выражения.
+///
+/// Каждый вариант хранит индекс токена в выражении, который привел к ошибке.
+/// Это позволяет подсветить виновный токен в интерфейсе.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CalcError {
+    /// У скобки нет пары.
+    UnbalancedParen(usize),
+    /// Операции или функции не хватает операнда.
+    MissingOperand(usize),
+    /// Аргумент функции вне области ее определения (например, корень из отрицательного числа).
+    Domain(usize),
+    /// Деление на ноль.
+    DivisionByZero(usize),
+    /// Результат не может быть представлен числом (бесконечность).
+    Overflow(usize),
+    /// Токен не может быть вычислен в данной позиции.
+    RejectedToken(usize),
+    /// Функции передано недопустимое количество аргументов.
+    Arity(usize),
+    /// Переменная не определена.
+    UndefinedVariable(usize),
+}
+
+impl CalcError {
+    /// Индекс токена, который привел к ошибке.
+    pub fn index(&self) -> usize {
+        match self {
+            CalcError::UnbalancedParen(i)
+            | CalcError::MissingOperand(i)
+            | CalcError::Domain(i)
+            | CalcError::DivisionByZero(i)
+            | CalcError::Overflow(i)
+            | CalcError::RejectedToken(i)
+            | CalcError::Arity(i)
+            | CalcError::UndefinedVariable(i) => { *i }
+        }
+    }
+
+    /// Сдвинуть индекс токена на `offset`.
+    ///
+    /// Используется, когда вычислялась только часть выражения (например, правая часть присваивания).
+    pub fn shift(self, offset: usize) -> Self {
+        match self {
+            CalcError::UnbalancedParen(i) => { CalcError::UnbalancedParen(i + offset) }
+            CalcError::MissingOperand(i) => { CalcError::MissingOperand(i + offset) }
+            CalcError::Domain(i) => { CalcError::Domain(i + offset) }
+            CalcError::DivisionByZero(i) => { CalcError::DivisionByZero(i + offset) }
+            CalcError::Overflow(i) => { CalcError::Overflow(i + offset) }
+            CalcError::RejectedToken(i) => { CalcError::RejectedToken(i + offset) }
+            CalcError::Arity(i) => { CalcError::Arity(i + offset) }
+            CalcError::UndefinedVariable(i) => { CalcError::UndefinedVariable(i + offset) }
+        }
+    }
+
+    /// Ключ сообщения об ошибке в каталоге.
+    pub fn message(&self) -> i18n::Msg {
+        match self {
+            CalcError::UnbalancedParen(_) => { i18n::Msg::UnbalancedParen }
+            CalcError::MissingOperand(_) => { i18n::Msg::MissingOperand }
+            CalcError::Domain(_) => { i18n::Msg::Domain }
+            CalcError::DivisionByZero(_) => { i18n::Msg::DivisionByZero }
+            CalcError::Overflow(_) => { i18n::Msg::Overflow }
+            CalcError::RejectedToken(_) => { i18n::Msg::RejectedToken }
+            CalcError::Arity(_) => { i18n::Msg::Arity }
+            CalcError::UndefinedVariable(_) => { i18n::Msg::UndefinedVariable }
+        }
+    }
+
+    /// Сообщение об ошибке на языке `lang`.
+    pub fn localized(&self, lang: i18n::Language) -> String {
+        i18n::tr(lang, self.message()).to_string()
+    }
+
+    /// Проверить результат вычисления токена с индексом `index`.
+    ///
+    /// `NaN` означает выход за область определения, а бесконечность - переполнение.
+    pub fn check(index: usize, val: f64) -> Result<f64, CalcError> {
+        if val.is_nan() {
+            Err(CalcError::Domain(index))
+        } else if val.is_infinite() {
+            Err(CalcError::Overflow(index))
+        } else { Ok(val) }
+    }
+}
+
+/// Сообщение на английском, независимо от языка интерфейса.
+/// В интерфейсе выводится [`CalcError::localized`].
+impl std::fmt::Display for CalcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.localized(i18n::Language::En))
+    }
+}
+
+impl std::error::Error for CalcError {}
+
+/// Ошибка разбора строки с выражением.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Позиция (номер символа в строке), начиная с которой не удалось выполнить разбор.
+    pub position: usize,
+    /// Фрагмент строки, который не удалось разобрать.
+    pub fragment: String,
+}
+
+impl ParseError {
+    /// Сообщение об ошибке на языке `lang`.
+    pub fn localized(&self, lang: i18n::Language) -> String {
+        i18n::trf(lang, i18n::Msg::ParseFailed, &[&self.fragment, &self.position])
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.localized(i18n::Language::En))
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -0,0 +1,283 @@
+use crate::env::Env;
+use crate::error::CalcError;
+use crate::math_exp;
+use crate::token;
+use crate::token::Weight;
+
+/// Абстрактное синтаксическое дерево выражения.
+///
+/// Строится из вектора токенов (см. [`Expr::try_from`]) и служит основой
+/// для инструментов поверх калькулятора: упрощения, вывода, дифференцирования.
+///
+/// Последнее поле каждого узла - индекс токена, из которого получен узел
+/// (для операций - индекс знака операции, для вызовов - индекс функции).
+/// Он попадает в ошибки вычисления, чтобы подсветить виновный токен,
+/// но не участвует в сравнении узлов.
+#[derive(Clone, Debug)]
+pub enum Expr {
+    /// Число (вещественное).
+    Number(f64, usize),
+    /// Математическая константа, хранится в символьном виде.
+    Constant(token::Const, usize),
+    /// Переменная, значение которой берется из окружения.
+    Variable(String, usize),
+    /// Результат предыдущего вычисления, значение которого берется из окружения.
+    Ans(usize),
+    /// Унарная операция: `Op::Neg` меняет знак, `Op::Pos` оставляет значение без изменений.
+    Unary(token::Op, Box<Expr>, usize),
+    /// Бинарная операция над левым и правым операндом.
+    Binary(token::Op, Box<Expr>, Box<Expr>, usize),
+    /// Вызов функции с аргументами.
+    Call(token::Func, Vec<Expr>, usize),
+}
+
+/// Деревья сравниваются по структуре: одинаковые выражения, стоявшие
+/// в разных местах исходной записи, равны.
+impl PartialEq for Expr {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Expr::Number(a, _), Expr::Number(b, _)) => { a == b }
+            (Expr::Constant(a, _), Expr::Constant(b, _)) => { a == b }
+            (Expr::Variable(a, _), Expr::Variable(b, _)) => { a == b }
+            (Expr::Ans(_), Expr::Ans(_)) => { true }
+            (Expr::Unary(op_a, a, _), Expr::Unary(op_b, b, _)) => { op_a == op_b && a == b }
+            (Expr::Binary(op_a, left_a, right_a, _), Expr::Binary(op_b, left_b, right_b, _)) => {
+                op_a == op_b && left_a == left_b && right_a == right_b
+            }
+            (Expr::Call(func_a, args_a, _), Expr::Call(func_b, args_b, _)) => { func_a == func_b && args_a == args_b }
+            _ => { false }
+        }
+    }
+}
+
+/// Посетитель узлов дерева.
+///
+/// Каждый метод получает содержимое узла. Обход дочерних узлов выполняет сам посетитель,
+/// вызывая [`Expr::accept`] для тех из них, которые ему нужны.
+///
+/// Константы и переменные посетитель обрабатывает сам, чтобы не потерять их имена.
+/// Предыдущий результат по умолчанию посещается как переменная `Ans`.
+pub trait Visitor {
+    type Output;
+    fn visit_number(&mut self, val: f64) -> Self::Output;
+    fn visit_constant(&mut self, c: &token::Const) -> Self::Output;
+    fn visit_variable(&mut self, name: &str) -> Self::Output;
+
+    fn visit_ans(&mut self) -> Self::Output {
+        self.visit_variable("Ans")
+    }
+
+    fn visit_unary(&mut self, op: &token::Op, operand: &Expr) -> Self::Output;
+    fn visit_binary(&mut self, op: &token::Op, left: &Expr, right: &Expr) -> Self::Output;
+    fn visit_call(&mut self, func: &token::Func, args: &[Expr]) -> Self::Output;
+}
+
+impl Expr {
+    /// Передать узел посетителю.
+    pub fn accept<V: Visitor>(&self, visitor: &mut V) -> V::Output {
+        match self {
+            Expr::Number(val, _) => { visitor.visit_number(*val) }
+            Expr::Constant(c, _) => { visitor.visit_constant(c) }
+            Expr::Variable(name, _) => { visitor.visit_variable(name) }
+            Expr::Ans(_) => { visitor.visit_ans() }
+            Expr::Unary(op, operand, _) => { visitor.visit_unary(op, operand) }
+            Expr::Binary(op, left, right, _) => { visitor.visit_binary(op, left, right) }
+            Expr::Call(func, args, _) => { visitor.visit_call(func, args) }
+        }
+    }
+
+    /// Индекс токена, из которого получен узел.
+    pub fn index(&self) -> usize {
+        match self {
+            Expr::Number(_, ind)
+            | Expr::Constant(_, ind)
+            | Expr::Variable(_, ind)
+            | Expr::Ans(ind)
+            | Expr::Unary(_, _, ind)
+            | Expr::Binary(_, _, _, ind)
+            | Expr::Call(_, _, ind) => { *ind }
+        }
+    }
+
+    /// Дочерние узлы в порядке слева направо.
+    pub fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Number(..) | Expr::Constant(..) | Expr::Variable(..) | Expr::Ans(_) => { vec![] }
+            Expr::Unary(_, operand, _) => { vec![operand.as_ref()] }
+            Expr::Binary(_, left, right, _) => { vec![left.as_ref(), right.as_ref()] }
+            Expr::Call(_, args, _) => { args.iter().collect() }
+        }
+    }
+
+    /// Обойти дерево в глубину, вызывая `f` для узла перед его дочерними узлами.
+    pub fn walk(&self, f: &mut impl FnMut(&Expr)) {
+        f(self);
+        for child in self.children() {
+            child.walk(f);
+        }
+    }
+
+    /// Вычислить значение выражения.
+    ///
+    /// Индекс в ошибке - это индекс токена узла, вызвавшего ошибку (см. [`Expr::index`]).
+    ///
+    /// Аргументы тригонометрических функций считаются заданными в радианах,
+    /// переменные и предыдущий результат не определены.
+    pub fn eval(&self) -> Result<f64, CalcError> {
+        self.eval_with(token::AngleMode::Rad, &Env::new())
+    }
+
+    /// Вычислить значение выражения, считая углы заданными в единицах `angle`
+    /// и беря значения переменных из `env`.
+    pub fn eval_with(&self, angle: token::AngleMode, env: &Env) -> Result<f64, CalcError> {
+        Evaluator { index: 0, angle, env }.eval(self)
+    }
+
+    /// Вес узла при выводе в инфиксной записи. Числа и функции не требуют скобок.
+    fn weight(&self) -> u8 {
+        match self {
+            Expr::Number(val, _) if val.is_sign_negative() => { token::Op::Neg.weight() }
+            Expr::Number(..) | Expr::Constant(..) | Expr::Variable(..) | Expr::Ans(_) | Expr::Call(..) => { u8::MAX }
+            Expr::Unary(op, _, _) => { op.weight() }
+            Expr::Binary(op, _, _, _) => { op.weight() }
+        }
+    }
+}
+
+/// Вычисление дерева. Индекс токена текущего узла попадает в ошибки.
+struct Evaluator<'a> {
+    index: usize,
+    angle: token::AngleMode,
+    env: &'a Env,
+}
+
+impl Evaluator<'_> {
+    /// Вычислить узел, запомнив индекс его токена.
+    fn eval(&mut self, expr: &Expr) -> Result<f64, CalcError> {
+        self.index = expr.index();
+        expr.accept(self)
+    }
+}
+
+impl Visitor for Evaluator<'_> {
+    type Output = Result<f64, CalcError>;
+
+    fn visit_number(&mut self, val: f64) -> Self::Output {
+        Ok(val)
+    }
+
+    fn visit_constant(&mut self, c: &token::Const) -> Self::Output {
+        Ok(c.value())
+    }
+
+    fn visit_variable(&mut self, name: &str) -> Self::Output {
+        self.env.get(name).ok_or(CalcError::UndefinedVariable(self.index))
+    }
+
+    fn visit_ans(&mut self) -> Self::Output {
+        self.env.ans().ok_or(CalcError::UndefinedVariable(self.index))
+    }
+
+    fn visit_unary(&mut self, op: &token::Op, operand: &Expr) -> Self::Output {
+        let ind = self.index;
+        let val = self.eval(operand)?;
+        op.apply_unary(ind, val)
+    }
+
+    fn visit_binary(&mut self, op: &token::Op, left: &Expr, right: &Expr) -> Self::Output {
+        let ind = self.index;
+        let first_val = self.eval(left)?;
+        let second_val = self.eval(right)?;
+        op.apply(ind, first_val, second_val)
+    }
+
+    fn visit_call(&mut self, func: &token::Func, args: &[Expr]) -> Self::Output {
+        let ind = self.index;
+        let mut values = Vec::with_capacity(args.len());
+        for arg in args {
+            values.push(self.eval(arg)?);
+        }
+        func.apply(ind, &values, self.angle)
+    }
+}
+
+impl TryFrom<&[token::Token]> for Expr {
+    type Error = CalcError;
+
+    /// Построить дерево из инфиксной последовательности токенов.
+    ///
+    /// Токены сначала преобразуются в обратную польскую запись, поэтому
+    /// индексы в ошибках совпадают с индексами токенов во входном срезе.
+    fn try_from(tokens: &[token::Token]) -> Result<Self, Self::Error> {
+        let mut stack: Vec<Expr> = Vec::new();
+        for math_exp::RpnToken { index: ind, token: t, argc } in math_exp::yard(tokens)? {
+            match t {
+                token::Token::Operand(val) => { stack.push(Expr::Number(val, ind)); }
+                token::Token::Constant(c) => { stack.push(Expr::Constant(c, ind)); }
+                token::Token::Variable(name) => { stack.push(Expr::Variable(name, ind)); }
+                token::Token::Ans => { stack.push(Expr::Ans(ind)); }
+                token::Token::Function(f) => {
+                    if !f.arity().accepts(argc) { return Err(CalcError::Arity(ind)); }
+                    if stack.len() < argc { return Err(CalcError::MissingOperand(ind)); }
+                    let args = stack.split_off(stack.len() - argc);
+                    stack.push(Expr::Call(f, args, ind));
+                }
+                token::Token::Operation(op) if op.is_unary() => {
+                    let operand = stack.pop().ok_or(CalcError::MissingOperand(ind))?;
+                    stack.push(Expr::Unary(op, Box::new(operand), ind));
+                }
+                token::Token::Operation(op) => {
+                    let right = stack.pop().ok_or(CalcError::MissingOperand(ind))?;
+                    let left = stack.pop().ok_or(CalcError::MissingOperand(ind))?;
+                    stack.push(Expr::Binary(op, Box::new(left), Box::new(right), ind));
+                }
+            }
+        }
+        // Ошибки те же, что и при вычислении по токенам (`MathExp::evaluate`).
+        match stack.len() {
+            0 => { Err(CalcError::MissingOperand(0)) }
+            1 => { Ok(stack.remove(0)) }
+            // Лишние значения без операции между ними.
+            _ => { Err(CalcError::RejectedToken(stack[1].index())) }
+        }
+    }
+}
+
+impl std::fmt::Display for Expr {
+    /// Вывод в инфиксной записи с минимальным количеством скобок,
+    /// достаточным для получения того же дерева при повторном разборе.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn child(f: &mut std::fmt::Formatter<'_>, e: &Expr, paren: bool) -> std::fmt::Result {
+            if paren { write!(f, "({})", e) } else { write!(f, "{}", e) }
+        }
+        match self {
+            Expr::Number(val, _) => { write!(f, "{}", val) }
+            Expr::Constant(c, _) => { write!(f, "{}", c) }
+            Expr::Variable(name, _) => { write!(f, "{}", name) }
+            Expr::Ans(_) => { write!(f, "Ans") }
+            Expr::Unary(op, operand, _) => {
+                write!(f, "{}", op)?;
+                child(f, operand, operand.weight() < op.weight())
+            }
+            Expr::Binary(op, left, right, _) => {
+                let weight = op.weight();
+                // Операнд с тем же весом берется в скобки, если он стоит
+                // не с той стороны, с которой группирует ассоциативность операции.
+                let right_assoc = op.assoc() == token::Assoc::Right;
+                child(f, left, left.weight() < weight || (left.weight() == weight && right_assoc))?;
+                write!(f, "{}", op)?;
+                // Унарный знак справа не требует скобок: `2^-1`.
+                let right_paren = right.weight() < weight || (right.weight() == weight && !right_assoc);
+                child(f, right, right_paren && !matches!(right.as_ref(), Expr::Unary(..)))
+            }
+            Expr::Call(func, args, _) => {
+                write!(f, "{}(", func)?;
+                for (ind, arg) in args.iter().enumerate() {
+                    if ind != 0 { write!(f, ",")?; }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
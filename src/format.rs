@@ -0,0 +1,419 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::i18n;
+use crate::i18n::Language;
+
+
+/// Десятичный разделитель.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DecimalSeparator {
+    /// Точка: `1234.5`.
+    #[default]
+    Point,
+    /// Запятая: `1234,5`. Аргументы функций при этом разделяются знаком `;`.
+    Comma,
+}
+
+/// Разделитель групп разрядов целой части.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Grouping {
+    /// Без разделения: `1234567`.
+    #[default]
+    None,
+    /// Пробел: `1 234 567`.
+    Space,
+    /// Запятая: `1,234,567`. При десятичной запятой группы разделяются точкой.
+    Comma,
+    /// Апостроф: `1'234'567`.
+    Apostrophe,
+}
+
+impl Grouping {
+    pub const ALL: [Grouping; 4] = [Grouping::None, Grouping::Space, Grouping::Comma, Grouping::Apostrophe];
+}
+
+/// Наибольшее количество знаков после разделителя в формате с фиксированной точностью
+/// и наибольшее количество значащих цифр. Дальше начинается погрешность двоичного представления.
+pub const MAX_DIGITS: usize = 15;
+
+/// Количество значащих цифр по умолчанию.
+pub const DEFAULT_SIGNIFICANT: usize = 12;
+
+/// Числа с порядком меньше этого выводятся в экспоненциальной записи.
+const MIN_EXPONENT: i32 = -6;
+
+/// Формат вывода чисел.
+///
+/// По умолчанию используется десятичная точка без разделения разрядов,
+/// а числа округляются до [`DEFAULT_SIGNIFICANT`] значащих цифр.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NumberFormat {
+    pub decimal: DecimalSeparator,
+    pub grouping: Grouping,
+    /// Количество знаков после разделителя. Если не задано, выводятся все значащие знаки.
+    pub digits: Option<usize>,
+    /// Количество значащих цифр, до которого округляется число.
+    /// Числа, которые в него не помещаются, выводятся в экспоненциальной записи.
+    /// Если не задано, число выводится точно, как в `f64::to_string`.
+    pub significant: Option<usize>,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            decimal: DecimalSeparator::Point,
+            grouping: Grouping::None,
+            digits: None,
+            significant: Some(DEFAULT_SIGNIFICANT),
+        }
+    }
+}
+
+impl NumberFormat {
+    /// Формат, принятый для языка `lang`: `1 234,5` для русского и `1,234.5` для английского.
+    ///
+    /// Используется, пока пользователь не выбрал формат сам. При десятичной запятой
+    /// аргументы функций разделяются знаком `;`.
+    pub fn for_language(lang: Language) -> Self {
+        match lang {
+            Language::Ru => { Self { decimal: DecimalSeparator::Comma, grouping: Grouping::Space, ..Self::default() } }
+            Language::En => { Self { decimal: DecimalSeparator::Point, grouping: Grouping::Comma, ..Self::default() } }
+        }
+    }
+
+    /// Тот же разделитель, но без разделения разрядов и округления: точное значение.
+    /// В этом виде число можно вставить обратно в выражение.
+    pub fn plain(&self) -> Self {
+        Self { decimal: self.decimal, grouping: Grouping::None, digits: None, significant: None }
+    }
+
+    /// Формат чисел выражения: разделители те же, но числа выводятся точно,
+    /// так как округляется и помечается только результат.
+    pub fn exact(&self) -> Self {
+        Self { digits: None, significant: None, ..*self }
+    }
+
+    pub fn decimal_char(&self) -> char {
+        match self.decimal {
+            DecimalSeparator::Point => { '.' }
+            DecimalSeparator::Comma => { ',' }
+        }
+    }
+
+    /// Разделитель аргументов функции.
+    pub fn separator(&self) -> &'static str {
+        match self.decimal {
+            DecimalSeparator::Point => { "," }
+            DecimalSeparator::Comma => { ";" }
+        }
+    }
+
+    /// Является ли строка десятичным разделителем при вводе.
+    pub fn is_decimal(&self, s: &str) -> bool {
+        s == "." || (s == "," && self.decimal == DecimalSeparator::Comma)
+    }
+
+    /// Является ли символ разделителем разрядов при вставке числа.
+    ///
+    /// Принимаются неразрывные пробелы и апостроф, а обычный пробел, запятая и точка -
+    /// только если разряды разделяются ими в этом формате. Иначе при вводе они означают
+    /// десятичный разделитель и разделитель аргументов.
+    pub fn is_group_char(&self, c: char) -> bool {
+        matches!(c, '\u{a0}' | '\u{202f}' | '\'')
+            || (c == ' ' && self.grouping == Grouping::Space)
+            || Some(c) == self.group_char()
+    }
+
+    fn group_char(&self) -> Option<char> {
+        match (self.grouping, self.decimal) {
+            (Grouping::None, _) => { None }
+            // Неразрывный пробел, чтобы число не переносилось по группам.
+            (Grouping::Space, _) => { Some('\u{a0}') }
+            (Grouping::Comma, DecimalSeparator::Point) => { Some(',') }
+            (Grouping::Comma, DecimalSeparator::Comma) => { Some('.') }
+            (Grouping::Apostrophe, _) => { Some('\'') }
+        }
+    }
+
+    /// Подпись клавиши раскладки: точка и запятая в раскладках означают
+    /// десятичный разделитель и разделитель аргументов.
+    pub fn key_label<'a>(&self, label: &'a str) -> &'a str {
+        match label {
+            "." => { if self.decimal == DecimalSeparator::Comma { "," } else { "." } }
+            "," => { self.separator() }
+            _ => { label }
+        }
+    }
+
+    /// Число в этом формате.
+    pub fn format(&self, val: f64) -> String {
+        self.format_rounded(val).0
+    }
+
+    /// Число в этом формате и признак того, что при выводе оно было округлено.
+    ///
+    /// Погрешность двоичного представления (`0.1+0.2 = 0.30000000000000004`)
+    /// округлением не считается: значение сравнивается с числом, округленным
+    /// до [`MAX_DIGITS`] значащих цифр. Без округления и фиксированных знаков
+    /// число выводится точно и округленным не считается.
+    pub fn format_rounded(&self, val: f64) -> (String, bool) {
+        if !val.is_finite() { return (val.to_string(), false); }
+        // Отрицательный ноль выводится как ноль.
+        let val = if val == 0.0 { 0.0 } else { val };
+        let fixed = |digits: usize| format!("{:.*}", digits.min(MAX_DIGITS), val);
+        let s = match self.significant {
+            Some(significant) => {
+                let significant = significant.clamp(1, MAX_DIGITS);
+                let sci = format!("{:.*e}", significant - 1, val);
+                let (mantissa, exp) = sci.split_once('e').unwrap_or((sci.as_str(), "0"));
+                let exp: i32 = exp.parse().unwrap_or_default();
+                if val != 0.0 && (exp >= significant as i32 || exp < MIN_EXPONENT) {
+                    format!("{}e{}", trim_zeros(mantissa), exp)
+                } else {
+                    match self.digits {
+                        Some(digits) => { fixed(digits) }
+                        None => { trim_zeros(&fixed((significant as i32 - 1 - exp).max(0) as usize)).to_string() }
+                    }
+                }
+            }
+            None => {
+                match self.digits {
+                    Some(digits) => { fixed(digits) }
+                    None => { val.to_string() }
+                }
+            }
+        };
+        // Число, округленное до нуля, выводится без знака.
+        let s = match s.strip_prefix('-') {
+            Some(abs) if abs.parse::<f64>() == Ok(0.0) => { abs.to_string() }
+            _ => { s }
+        };
+        let rounded = if self.significant.is_none() && self.digits.is_none() {
+            // Число выведено точно.
+            false
+        } else {
+            let exact = format!("{:.*e}", MAX_DIGITS - 1, val).parse::<f64>().unwrap_or(val);
+            s.parse::<f64>().map_or(false, |shown| shown != exact)
+        };
+        let s = match s.split_once('e') {
+            Some((mantissa, exp)) => { format!("{}e{}", self.format_input(mantissa), exp) }
+            None => { self.format_input(&s) }
+        };
+        (s, rounded)
+    }
+
+    /// Число, записанное с десятичной точкой (например, вводимое значение `1234.`),
+    /// с разделителями этого формата. Знаки числа не меняются.
+    pub fn format_input(&self, s: &str) -> String {
+        let (sign, s) = match s.strip_prefix('-') {
+            Some(rest) => { ("-", rest) }
+            None => { ("", s) }
+        };
+        let (int, frac) = match s.split_once('.') {
+            Some((int, frac)) => { (int, Some(frac)) }
+            None => { (s, None) }
+        };
+        let mut out = sign.to_string();
+        match self.group_char() {
+            Some(sep) => {
+                let len = int.len();
+                for (ind, c) in int.chars().enumerate() {
+                    if ind > 0 && (len - ind) % 3 == 0 { out.push(sep); }
+                    out.push(c);
+                }
+            }
+            None => { out.push_str(int); }
+        }
+        if let Some(frac) = frac {
+            out.push(self.decimal_char());
+            out.push_str(frac);
+        }
+        out
+    }
+
+    /// Настройки формата для меню.
+    pub fn editor(&mut self, ui: &mut egui::Ui, lang: Language) {
+        if ui.button(i18n::tr(lang, i18n::Msg::FormatForLanguage)).clicked() {
+            *self = Self { digits: self.digits, significant: self.significant, ..Self::for_language(lang) };
+        }
+        ui.separator();
+        ui.radio_value(&mut self.decimal, DecimalSeparator::Point, i18n::tr(lang, i18n::Msg::DecimalPoint));
+        ui.radio_value(&mut self.decimal, DecimalSeparator::Comma, i18n::tr(lang, i18n::Msg::DecimalComma));
+        ui.separator();
+        for grouping in Grouping::ALL {
+            let msg = match grouping {
+                Grouping::None => { i18n::Msg::GroupNone }
+                Grouping::Space => { i18n::Msg::GroupSpace }
+                Grouping::Comma => { i18n::Msg::GroupComma }
+                Grouping::Apostrophe => { i18n::Msg::GroupApostrophe }
+            };
+            ui.radio_value(&mut self.grouping, grouping, i18n::tr(lang, msg));
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            let mut fixed = self.digits.is_some();
+            let mut digits = self.digits.unwrap_or(2);
+            ui.checkbox(&mut fixed, i18n::tr(lang, i18n::Msg::FixedDigits));
+            ui.add_enabled(fixed, egui::DragValue::new(&mut digits).clamp_range(0..=MAX_DIGITS));
+            self.digits = fixed.then_some(digits);
+        });
+        ui.horizontal(|ui| {
+            let mut rounding = self.significant.is_some();
+            let mut significant = self.significant.unwrap_or(DEFAULT_SIGNIFICANT);
+            ui.checkbox(&mut rounding, i18n::tr(lang, i18n::Msg::SignificantDigits));
+            ui.add_enabled(rounding, egui::DragValue::new(&mut significant).clamp_range(1..=MAX_DIGITS));
+            self.significant = rounding.then_some(significant);
+        });
+    }
+}
+
+/// Убрать незначащие нули дробной части: `1.500` -> `1.5`, `2.000` -> `2`.
+fn trim_zeros(s: &str) -> &str {
+    if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.')
+    } else { s }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RU: NumberFormat = NumberFormat {
+        decimal: DecimalSeparator::Comma,
+        grouping: Grouping::Space,
+        digits: None,
+        significant: Some(DEFAULT_SIGNIFICANT),
+    };
+
+    #[test]
+    fn language_formats() {
+        assert_eq!(NumberFormat::for_language(Language::Ru), RU);
+        assert_eq!(NumberFormat::for_language(Language::Ru).format(1234.5), "1\u{a0}234,5");
+        assert_eq!(NumberFormat::for_language(Language::En).format(1234.5), "1,234.5");
+        assert_eq!(NumberFormat::default().format(1234.5), "1234.5");
+    }
+
+    #[test]
+    fn group_chars() {
+        let format = |decimal, grouping| NumberFormat { decimal, grouping, ..NumberFormat::default() };
+        assert_eq!(format(DecimalSeparator::Point, Grouping::None).group_char(), None);
+        assert_eq!(format(DecimalSeparator::Point, Grouping::Space).group_char(), Some('\u{a0}'));
+        assert_eq!(format(DecimalSeparator::Point, Grouping::Comma).group_char(), Some(','));
+        assert_eq!(format(DecimalSeparator::Comma, Grouping::Comma).group_char(), Some('.'));
+        assert_eq!(format(DecimalSeparator::Comma, Grouping::Apostrophe).group_char(), Some('\''));
+        assert_eq!(format(DecimalSeparator::Comma, Grouping::Comma).format(1234567.5), "1.234.567,5");
+        assert_eq!(format(DecimalSeparator::Point, Grouping::Apostrophe).format(1234567.0), "1'234'567");
+    }
+
+    #[test]
+    fn paste_group_chars() {
+        assert!(RU.is_group_char(' '));
+        assert!(RU.is_group_char('\u{a0}'));
+        assert!(NumberFormat::default().is_group_char('\''));
+        assert!(NumberFormat::default().is_group_char('\u{202f}'));
+        assert!(!NumberFormat::default().is_group_char(' '));
+        assert!(NumberFormat::for_language(Language::En).is_group_char(','));
+        assert!(!NumberFormat::for_language(Language::En).is_group_char('.'));
+        assert!(!NumberFormat::default().is_group_char(','));
+        assert!(!RU.is_group_char('.'));
+        let comma = NumberFormat { grouping: Grouping::Comma, ..RU };
+        assert!(comma.is_group_char('.'));
+        assert!(!comma.is_group_char(','));
+    }
+
+    #[test]
+    fn separators() {
+        let point = NumberFormat::default();
+        assert_eq!(point.decimal_char(), '.');
+        assert_eq!(point.separator(), ",");
+        assert_eq!(point.key_label("."), ".");
+        assert_eq!(point.key_label(","), ",");
+        assert_eq!(point.key_label("sin"), "sin");
+        assert_eq!(RU.decimal_char(), ',');
+        assert_eq!(RU.separator(), ";");
+        assert_eq!(RU.key_label("."), ",");
+        assert_eq!(RU.key_label(","), ";");
+    }
+
+    #[test]
+    fn decimal_input() {
+        let point = NumberFormat::default();
+        assert!(point.is_decimal("."));
+        assert!(!point.is_decimal(","));
+        assert!(RU.is_decimal("."));
+        assert!(RU.is_decimal(","));
+        assert!(!RU.is_decimal(";"));
+    }
+
+    fn rounded(format: NumberFormat, val: f64) -> (String, bool) {
+        format.format_rounded(val)
+    }
+
+    #[test]
+    fn binary_error_is_not_rounding() {
+        let exact = NumberFormat { significant: None, ..NumberFormat::default() };
+        assert_eq!(rounded(NumberFormat::default(), 0.1 + 0.2), ("0.3".to_string(), false));
+        assert_eq!(rounded(exact, 0.1 + 0.2), ("0.30000000000000004".to_string(), false));
+        assert_eq!(rounded(exact, 1.0 / 3.0), ((1.0f64 / 3.0).to_string(), false));
+    }
+
+    #[test]
+    fn rounding_flag() {
+        let format = NumberFormat::default();
+        assert_eq!(rounded(format, 1.0 / 3.0), ("0.333333333333".to_string(), true));
+        assert_eq!(rounded(format, 9.999999999999e11), ("1e12".to_string(), true));
+        assert_eq!(rounded(format, 2.5), ("2.5".to_string(), false));
+        let significant = NumberFormat { significant: Some(3), ..format };
+        assert_eq!(rounded(significant, 1234.0), ("1.23e3".to_string(), true));
+        assert_eq!(rounded(significant, 0.125), ("0.125".to_string(), false));
+    }
+
+    #[test]
+    fn exponent_notation() {
+        let format = NumberFormat::default();
+        assert_eq!(rounded(format, 1e15), ("1e15".to_string(), false));
+        assert_eq!(rounded(format, 1e-7), ("1e-7".to_string(), false));
+        assert_eq!(rounded(format, -2.5e-7), ("-2.5e-7".to_string(), false));
+        assert_eq!(rounded(format, 123456789012.0), ("123456789012".to_string(), false));
+        assert_eq!(rounded(format, 0.000001), ("0.000001".to_string(), false));
+    }
+
+    #[test]
+    fn fixed_and_significant_digits() {
+        let fixed = NumberFormat { digits: Some(2), ..NumberFormat::default() };
+        assert_eq!(rounded(fixed, 1234.5678), ("1234.57".to_string(), true));
+        assert_eq!(rounded(fixed, 2.5), ("2.50".to_string(), false));
+        // Число, которое не помещается в значащие цифры, выводится в экспоненциальной записи.
+        assert_eq!(rounded(fixed, 1e15), ("1e15".to_string(), false));
+        let fixed_only = NumberFormat { significant: None, ..fixed };
+        assert_eq!(rounded(fixed_only, 1.0 / 3.0), ("0.33".to_string(), true));
+        assert_eq!(rounded(fixed_only, 1e15), ("1000000000000000.00".to_string(), false));
+    }
+
+    #[test]
+    fn special_values() {
+        let format = NumberFormat::default();
+        assert_eq!(rounded(format, f64::NAN), ("NaN".to_string(), false));
+        assert_eq!(rounded(format, f64::INFINITY), ("inf".to_string(), false));
+        assert_eq!(rounded(format, f64::NEG_INFINITY), ("-inf".to_string(), false));
+    }
+
+    #[test]
+    fn negative_zero() {
+        let fixed = NumberFormat { digits: Some(2), ..NumberFormat::default() };
+        assert_eq!(rounded(NumberFormat::default(), -0.0), ("0".to_string(), false));
+        assert_eq!(rounded(NumberFormat::default().plain(), -0.0), ("0".to_string(), false));
+        assert_eq!(rounded(fixed, -0.0), ("0.00".to_string(), false));
+        assert_eq!(rounded(fixed, -0.001), ("0.00".to_string(), true));
+    }
+
+    #[test]
+    fn negative_numbers() {
+        assert_eq!(RU.format(-1234567.25), "-1\u{a0}234\u{a0}567,25");
+        assert_eq!(NumberFormat::for_language(Language::En).format(-123.5), "-123.5");
+        assert_eq!(NumberFormat::for_language(Language::En).format(-1234.0), "-1,234");
+        assert_eq!(RU.format_input("-1234."), "-1\u{a0}234,");
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::VecDeque;
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use crate::format::NumberFormat;
+use crate::i18n;
+use crate::math_exp;
+use crate::theme::Theme;
+use crate::token;
+
+
+/// Количество записей, которое хранит история по умолчанию.
+pub const HISTORY_CAPACITY: usize = 100;
+
+/// Запись истории: вычисленное выражение и его результат.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Токены выражения в том виде, в котором оно было вычислено.
+    pub tokens: Vec<token::Token>,
+    /// Результат вычисления.
+    pub result: f64,
+    /// Время вычисления в секундах от начала эпохи Unix.
+    pub timestamp: f64,
+}
+
+impl HistoryEntry {
+    /// Выражение в виде строки с именами функций в нотации языка `lang`
+    /// и числами в формате `format`.
+    pub fn expression(&self, lang: i18n::Language, format: &NumberFormat) -> String {
+        self.tokens.iter().map(|t| t.formatted(lang, format)).collect()
+    }
+}
+
+/// История вычислений.
+///
+/// Записи хранятся от старых к новым. При превышении вместимости
+/// самые старые записи удаляются.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct History {
+    entries: VecDeque<HistoryEntry>,
+    capacity: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::with_capacity(HISTORY_CAPACITY)
+    }
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Создать историю, которая хранит не больше `capacity` записей.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Добавить запись с текущим временем.
+    pub fn push(&mut self, tokens: Vec<token::Token>, result: f64) {
+        self.push_entry(HistoryEntry { tokens, result, timestamp: now() });
+    }
+
+    /// Добавить готовую запись.
+    pub fn push_entry(&mut self, entry: HistoryEntry) {
+        if self.capacity == 0 { return; }
+        while self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// Запись с индексом `index` (0 - самая старая).
+    pub fn get(&self, index: usize) -> Option<&HistoryEntry> {
+        self.entries.get(index)
+    }
+
+    /// Последняя запись.
+    pub fn last(&self) -> Option<&HistoryEntry> {
+        self.entries.back()
+    }
+
+    /// Записи от старых к новым.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &HistoryEntry> + ExactSizeIterator {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Удалить все записи.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Текущее время в секундах от начала эпохи Unix.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64())
+}
+
+/// Текущее время в секундах от начала эпохи Unix.
+#[cfg(target_arch = "wasm32")]
+pub fn now() -> f64 {
+    js_sys::Date::now() / 1000.0
+}
+
+/// Сколько времени прошло с момента `timestamp`, в виде "5 мин назад".
+fn format_age(timestamp: f64, lang: i18n::Language) -> String {
+    let seconds = (now() - timestamp).max(0.0) as u64;
+    match seconds {
+        0..=59 => { i18n::tr(lang, i18n::Msg::JustNow).to_string() }
+        60..=3599 => { i18n::trf(lang, i18n::Msg::MinutesAgo, &[&(seconds / 60)]) }
+        3600..=86399 => { i18n::trf(lang, i18n::Msg::HoursAgo, &[&(seconds / 3600)]) }
+        _ => { i18n::trf(lang, i18n::Msg::DaysAgo, &[&(seconds / 86400)]) }
+    }
+}
+
+/// Действие, выбранное в панели истории.
+enum HistoryAction {
+    /// Загрузить выражение записи.
+    Load(usize),
+    /// Вставить результат записи как число.
+    Insert(usize),
+    /// Очистить историю.
+    Clear,
+}
+
+/// Панель с историей вычислений.
+///
+/// Нажатие на выражение загружает его обратно в редактор,
+/// нажатие на результат вставляет его в текущее выражение.
+pub struct HistoryPanel<'a> {
+    buffer: &'a mut math_exp::MathExp,
+    theme: Option<&'a Theme>,
+    pub max_height: f32,
+}
+
+impl<'a> HistoryPanel<'a> {
+    pub fn from_buffer(buffer: &'a mut math_exp::MathExp) -> Self {
+        Self {
+            buffer,
+            theme: None,
+            max_height: 120.0,
+        }
+    }
+
+    /// Задать тему, из которой берется цвет результатов.
+    pub fn theme(mut self, theme: &'a Theme) -> Self {
+        self.theme = Some(theme);
+        self
+    }
+
+    pub fn show(self, ui: &mut egui::Ui) {
+        let mut action = None;
+        let result_color = self.theme.map_or(egui::Color32::LIGHT_GREEN, |theme| theme.result);
+        let lang = self.buffer.language();
+        let format = self.buffer.number_format();
+        egui::CollapsingHeader::new(i18n::trf(lang, i18n::Msg::History, &[&self.buffer.history().len()]))
+            .id_source("history_panel")
+            .show(ui, |ui| {
+                if self.buffer.history().is_empty() {
+                    ui.weak(i18n::tr(lang, i18n::Msg::HistoryEmpty));
+                    return;
+                }
+                egui::ScrollArea::vertical()
+                    .max_height(self.max_height)
+                    .show(ui, |ui| {
+                        // Новые записи показываем сверху.
+                        for (ind, entry) in self.buffer.history().iter().enumerate().rev() {
+                            ui.horizontal(|ui| {
+                                let expression = egui::RichText::new(entry.expression(lang, &format.exact())).monospace();
+                                if ui.small_button(expression)
+                                    .on_hover_text(format_age(entry.timestamp, lang))
+                                    .clicked() {
+                                    action = Some(HistoryAction::Load(ind));
+                                }
+                                let (result, rounded) = format.format_rounded(entry.result);
+                                let result = egui::RichText::new(format!("{} {}", if rounded { "≈" } else { "=" }, result))
+                                    .monospace()
+                                    .color(result_color);
+                                if ui.small_button(result).clicked() {
+                                    action = Some(HistoryAction::Insert(ind));
+                                }
+                            });
+                        }
+                    });
+                if ui.small_button(i18n::tr(lang, i18n::Msg::HistoryClear)).clicked() {
+                    action = Some(HistoryAction::Clear);
+                }
+            });
+
+        match action {
+            Some(HistoryAction::Load(ind)) => {
+                if let Some(entry) = self.buffer.history().get(ind).cloned() {
+                    self.buffer.load_tokens(entry.tokens);
+                }
+            }
+            Some(HistoryAction::Insert(ind)) => {
+                if let Some(entry) = self.buffer.history().get(ind).cloned() {
+                    self.buffer.insert_operand(entry.result);
+                }
+            }
+            Some(HistoryAction::Clear) => { self.buffer.history_mut().clear(); }
+            None => {}
+        }
+    }
+}
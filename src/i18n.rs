@@ -0,0 +1,203 @@
+use serde::{Deserialize, Serialize};
+
+
+/// Язык интерфейса и сообщений.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    Ru,
+    En,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::Ru, Language::En];
+
+    /// Язык по коду локали: `ru`, `ru-RU`, `en_US.UTF-8` и т.п.
+    pub fn from_locale(locale: &str) -> Option<Self> {
+        let code = locale.split(['-', '_', '.']).next()?;
+        match code.to_ascii_lowercase().as_str() {
+            "ru" => { Some(Language::Ru) }
+            "en" => { Some(Language::En) }
+            _ => { None }
+        }
+    }
+
+    /// Язык системы или браузера, если он поддерживается.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn detect() -> Option<Self> {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find_map(|locale| Self::from_locale(&locale))
+    }
+
+    /// Язык системы или браузера, если он поддерживается.
+    #[cfg(target_arch = "wasm32")]
+    pub fn detect() -> Option<Self> {
+        let navigator = js_sys::Reflect::get(&js_sys::global(), &"navigator".into()).ok()?;
+        let language = js_sys::Reflect::get(&navigator, &"language".into()).ok()?.as_string()?;
+        Self::from_locale(&language)
+    }
+
+    /// Название языка на нем самом.
+    pub fn title(&self) -> &'static str {
+        match self {
+            Language::Ru => { "Русский" }
+            Language::En => { "English" }
+        }
+    }
+}
+
+/// Ключи сообщений каталога.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Msg {
+    // Ошибки ввода и вычисления.
+    TokenRejected,
+    ParseFailed,
+    UnbalancedParen,
+    MissingOperand,
+    Domain,
+    DivisionByZero,
+    Overflow,
+    RejectedToken,
+    Arity,
+    UndefinedVariable,
+    // История.
+    History,
+    HistoryEmpty,
+    HistoryClear,
+    JustNow,
+    MinutesAgo,
+    HoursAgo,
+    DaysAgo,
+    // Экран и клавиатура.
+    Input,
+    CopyResult,
+    CopyExpression,
+    SwitchLayout,
+    LayoutBasic,
+    LayoutScientific,
+    Language,
+    // Формат чисел.
+    NumberFormat,
+    FormatForLanguage,
+    DecimalPoint,
+    DecimalCommaRejected,
+    DecimalComma,
+    GroupNone,
+    GroupSpace,
+    GroupComma,
+    GroupApostrophe,
+    FixedDigits,
+    SignificantDigits,
+    // Загрузка раскладок.
+    LoadLayout,
+    LoadLayoutHint,
+    Load,
+    LayoutLoaded,
+    LayoutIo,
+    LayoutParse,
+    LayoutUnknownFormat,
+    LayoutEmpty,
+    LayoutInvalidRow,
+    // Темы.
+    Theme,
+    ThemeHint,
+    ThemeCustomize,
+    ThemeDark,
+    ThemeLight,
+    ThemeHighContrast,
+    ThemeCustom,
+    DarkBase,
+    Borders,
+    Background,
+    Display,
+    Expression,
+    Result,
+    Error,
+    DigitKeys,
+    OperatorKeys,
+    FunctionKeys,
+    ActionKeys,
+}
+
+/// Текст сообщения `msg` на языке `lang`. Параметры обозначаются `{0}`, `{1}`, ...
+pub fn tr(lang: Language, msg: Msg) -> &'static str {
+    let [ru, en] = match msg {
+        Msg::TokenRejected => { ["Токен {0} не может быть добавлен после {1}", "Token {0} cannot follow {1}"] }
+        Msg::ParseFailed => { ["Не удалось разобрать \"{0}\" в позиции {1}", "Cannot parse \"{0}\" at position {1}"] }
+        Msg::UnbalancedParen => { ["В выражении отсутствует скобка.", "Unbalanced parenthesis."] }
+        Msg::MissingOperand => { ["В выражении не хватает операнда.", "Missing operand."] }
+        Msg::Domain => { ["Аргумент вне области определения.", "Argument out of domain."] }
+        Msg::DivisionByZero => { ["Деление на ноль.", "Division by zero."] }
+        Msg::Overflow => { ["Переполнение.", "Overflow."] }
+        Msg::RejectedToken => { ["Токен не может быть вычислен.", "Token cannot be evaluated."] }
+        Msg::Arity => { ["Неверное количество аргументов функции.", "Wrong number of function arguments."] }
+        Msg::UndefinedVariable => { ["Переменная не определена.", "Undefined variable."] }
+        Msg::History => { ["История ({0})", "History ({0})"] }
+        Msg::HistoryEmpty => { ["Вычислений еще не было.", "No calculations yet."] }
+        Msg::HistoryClear => { ["Очистить историю", "Clear history"] }
+        Msg::JustNow => { ["только что", "just now"] }
+        Msg::MinutesAgo => { ["{0} мин назад", "{0} min ago"] }
+        Msg::HoursAgo => { ["{0} ч назад", "{0} h ago"] }
+        Msg::DaysAgo => { ["{0} дн назад", "{0} d ago"] }
+        Msg::Input => { ["Ввод: {0}", "Input: {0}"] }
+        Msg::CopyResult => { ["Копировать результат", "Copy result"] }
+        Msg::CopyExpression => { ["Копировать выражение", "Copy expression"] }
+        Msg::SwitchLayout => { ["Сменить раскладку", "Switch layout"] }
+        Msg::LayoutBasic => { ["Обычный", "Basic"] }
+        Msg::LayoutScientific => { ["Инженерный", "Scientific"] }
+        Msg::Language => { ["Язык", "Language"] }
+        Msg::NumberFormat => { ["Формат чисел", "Number format"] }
+        Msg::FormatForLanguage => { ["Как принято в языке", "Language default"] }
+        Msg::DecimalPoint => { ["Десятичная точка", "Decimal point"] }
+        Msg::DecimalComma => { ["Десятичная запятая", "Decimal comma"] }
+        Msg::DecimalCommaRejected => { ["Запятая здесь не может быть десятичной. Аргументы функции разделяются знаком «;».", "A decimal comma is not allowed here. Separate function arguments with \";\"."] }
+        Msg::GroupNone => { ["Без разделения разрядов", "No digit grouping"] }
+        Msg::GroupSpace => { ["Разряды через пробел", "Group with spaces"] }
+        Msg::GroupComma => { ["Разряды через запятую", "Group with commas"] }
+        Msg::GroupApostrophe => { ["Разряды через апостроф", "Group with apostrophes"] }
+        Msg::FixedDigits => { ["Знаков после запятой", "Decimal places"] }
+        Msg::SignificantDigits => { ["Значащих цифр", "Significant digits"] }
+        Msg::LoadLayout => { ["Загрузить раскладку…", "Load layout…"] }
+        Msg::LoadLayoutHint => { ["Вставьте раскладку в формате JSON или TOML или перетащите файл в окно.", "Paste a layout in JSON or TOML or drop a file onto the window."] }
+        Msg::Load => { ["Загрузить", "Load"] }
+        Msg::LayoutLoaded => { ["Раскладка «{0}» загружена.", "Layout \"{0}\" loaded."] }
+        Msg::LayoutIo => { ["Не удалось прочитать раскладку: {0}", "Cannot read layout: {0}"] }
+        Msg::LayoutParse => { ["Не удалось разобрать раскладку: {0}", "Cannot parse layout: {0}"] }
+        Msg::LayoutUnknownFormat => { ["Неизвестный формат раскладки: \"{0}\"", "Unknown layout format: \"{0}\""] }
+        Msg::LayoutEmpty => { ["Раскладка не содержит клавиш.", "Layout has no keys."] }
+        Msg::LayoutInvalidRow => { ["Неверная ширина строки {0} раскладки.", "Layout row {0} has invalid width."] }
+        Msg::Theme => { ["Тема", "Theme"] }
+        Msg::ThemeHint => { ["Тема: {0}", "Theme: {0}"] }
+        Msg::ThemeCustomize => { ["Настроить цвета…", "Customize colors…"] }
+        Msg::ThemeDark => { ["Темная", "Dark"] }
+        Msg::ThemeLight => { ["Светлая", "Light"] }
+        Msg::ThemeHighContrast => { ["Контрастная", "High contrast"] }
+        Msg::ThemeCustom => { ["Своя", "Custom"] }
+        Msg::DarkBase => { ["Темная основа", "Dark base"] }
+        Msg::Borders => { ["Контрастные границы", "Contrast borders"] }
+        Msg::Background => { ["Фон", "Background"] }
+        Msg::Display => { ["Экран", "Display"] }
+        Msg::Expression => { ["Выражение", "Expression"] }
+        Msg::Result => { ["Результат", "Result"] }
+        Msg::Error => { ["Ошибка", "Error"] }
+        Msg::DigitKeys => { ["Цифры", "Digits"] }
+        Msg::OperatorKeys => { ["Операции", "Operators"] }
+        Msg::FunctionKeys => { ["Функции", "Functions"] }
+        Msg::ActionKeys => { ["Действия", "Actions"] }
+    };
+    match lang {
+        Language::Ru => { ru }
+        Language::En => { en }
+    }
+}
+
+/// Текст сообщения с подставленными параметрами.
+pub fn trf(lang: Language, msg: Msg, args: &[&dyn std::fmt::Display]) -> String {
+    let mut s = tr(lang, msg).to_string();
+    for (ind, arg) in args.iter().enumerate() {
+        s = s.replace(&format!("{{{}}}", ind), &arg.to_string());
+    }
+    s
+}
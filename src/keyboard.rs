@@ -1,19 +1,181 @@
 use eframe::egui;
 use eframe::egui::Widget;
+use crate::layout::{KeyAction, KeyboardLayout};
+use crate::i18n;
 use crate::math_exp;
+use crate::theme::{KeyCategory, KeyColors, Theme};
+use crate::token;
 
 
-static KEYS: [&str; 25] = [
-    "√", "C", "(", ")", "<=",
-    "sin", "7", "8", "9", "*",
-    "cos", "4", "5", "6", "/",
-    "tg", "1", "2", "3", "-",
-    "ctg", ".", "0", "=", "+"
+/// Варианты тригонометрических клавиш в зависимости от модификаторов:
+/// без модификаторов, INV, HYP, INV + HYP.
+static TRIG_KEYS: [[&str; 4]; 4] = [
+    ["sin", "arcsin", "sh", "arsh"],
+    ["cos", "arccos", "ch", "arch"],
+    ["tg", "arctg", "th", "arth"],
+    ["ctg", "arcctg", "cth", "arcth"],
 ];
 
+/// Состояние клавиш-модификаторов. Хранится в памяти egui между кадрами.
+#[derive(Clone, Copy, Default)]
+struct Modifiers {
+    /// Обратная функция.
+    inv: bool,
+    /// Гиперболическая функция.
+    hyp: bool,
+}
+
+impl Modifiers {
+    /// Подпись клавиши с учетом модификаторов.
+    fn apply<'t>(&self, title: &'t str) -> &'t str {
+        let variant = self.inv as usize + 2 * self.hyp as usize;
+        match TRIG_KEYS.iter().find(|keys| keys[0] == title) {
+            Some(keys) => { keys[variant] }
+            None => { title }
+        }
+    }
+}
+
+/// Сколько секунд подсвечивается клавиша, нажатая на физической клавиатуре.
+const FLASH_DURATION: f64 = 0.15;
+
+/// Состояние ввода с физической клавиатуры. Хранится в памяти egui между кадрами.
+#[derive(Clone, Default)]
+struct HardwareInput {
+    /// Набираемое имя функции, константы или переменной.
+    pending: String,
+    /// Подпись подсвеченной клавиши и время нажатия.
+    flash: Option<(String, f64)>,
+}
+
+impl HardwareInput {
+    /// Подсветить клавишу с подписью `title`.
+    fn flash(&mut self, title: impl Into<String>, time: f64) {
+        self.flash = Some((title.into(), time));
+    }
+
+    /// Подсвечена ли клавиша с подписью `title` в момент `time`.
+    fn is_flashed(&self, title: &str, time: f64) -> bool {
+        matches!(&self.flash, Some((t, start)) if t == title && time - start < FLASH_DURATION)
+    }
+
+    /// Добавить набранное имя в выражение.
+    ///
+    /// Возвращает `true`, если имя оказалось функцией: открывающаяся скобка
+    /// в этом случае уже вставлена вместе с ней.
+    fn flush(&mut self, buffer: &mut math_exp::MathExp, time: f64) -> bool {
+        if self.pending.is_empty() { return false; }
+        let name = std::mem::take(&mut self.pending);
+        let is_function = token::Func::try_from(name.as_str()).is_ok();
+        if buffer.add(name.as_str()) {
+            // Подсвечиваем клавишу по отображаемому имени: `tan` подсветит `tg`.
+            if let Ok(t) = token::Token::try_from(name.as_str()) { self.flash(t.to_string(), time); }
+        }
+        is_function
+    }
+
+    /// Обработать события физической клавиатуры и буфера обмена.
+    fn handle(&mut self, ctx: &egui::Context, buffer: &mut math_exp::MathExp, time: f64) {
+        let events = ctx.input(|i| i.events.clone());
+        for event in &events {
+            match event {
+                egui::Event::Copy => {
+                    self.flush(buffer, time);
+                    let text = buffer.copy_text();
+                    if !text.is_empty() { ctx.output_mut(|o| o.copied_text = text); }
+                }
+                // Вырезание копирует выражение и очищает его.
+                egui::Event::Cut => {
+                    self.flush(buffer, time);
+                    let text = buffer.copy_text();
+                    if !text.is_empty() {
+                        ctx.output_mut(|o| o.copied_text = text);
+                        buffer.clear();
+                        self.flash("C", time);
+                    }
+                }
+                egui::Event::Paste(text) => {
+                    self.flush(buffer, time);
+                    // Ошибка разбора сохраняется в выражении и подсвечивается на экране.
+                    let _ = buffer.paste(text);
+                }
+                egui::Event::Key { key, pressed: true, .. } => {
+                    match key {
+                        egui::Key::Enter => {
+                            self.flush(buffer, time);
+                            buffer.calculate();
+                            self.flash("=", time);
+                        }
+                        egui::Key::Backspace => {
+                            if self.pending.pop().is_none() { buffer.pop(); }
+                            self.flash("<=", time);
+                        }
+                        egui::Key::Escape => {
+                            self.pending.clear();
+                            buffer.clear();
+                            self.flash("C", time);
+                        }
+                        _ => {}
+                    }
+                }
+                egui::Event::Text(text) => {
+                    for c in text.chars() {
+                        // Буквы и следующие за ними цифры набирают имя (например, log2).
+                        if c.is_alphabetic() || (c.is_ascii_digit() && !self.pending.is_empty()) {
+                            self.pending.push(c);
+                            continue;
+                        }
+                        // Скобка после имени, которое не является функцией, отклоняется,
+                        // а имя остается набранным, чтобы опечатку можно было исправить.
+                        if c == '(' && !self.pending.is_empty() && token::Func::try_from(self.pending.as_str()).is_err() {
+                            continue;
+                        }
+                        let after_function = self.flush(buffer, time);
+                        match c {
+                            // После имени переменной в начале выражения знак означает присваивание.
+                            '=' => {
+                                if matches!(buffer.tokens(), [token::Token::Variable(_)]) {
+                                    buffer.add("=");
+                                } else { buffer.calculate(); }
+                                self.flash("=", time);
+                            }
+                            // Скобка после имени функции уже вставлена.
+                            '(' if after_function => {}
+                            _ if c.is_whitespace() => {}
+                            _ => {
+                                let s = c.to_string();
+                                if buffer.add(s.as_str()) {
+                                    // В раскладках десятичный разделитель записан точкой,
+                                    // а разделитель аргументов - запятой.
+                                    let key = if buffer.number_format().is_decimal(&s) {
+                                        "."
+                                    } else if s == ";" { "," } else { s.as_str() };
+                                    self.flash(key, time);
+                                }
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Размер моноширинного шрифта, при котором строка из `chars` символов
+/// помещается в ширину `width`, но не больше `max` и не меньше 12 точек.
+///
+/// Все величины задаются в точках egui, поэтому результат не зависит от `pixels_per_point`.
+pub fn fit_font_size(width: f32, chars: f32, max: f32) -> f32 {
+    // Ширина символа моноширинного шрифта примерно 0.6 от его размера.
+    let fit = width / (chars.max(1.0) * 0.6);
+    fit.min(max).max(12.0)
+}
 
 pub struct CalcKeyboard<'a> {
     buffer: &'a mut math_exp::MathExp,
+    layout: Option<&'a KeyboardLayout>,
+    theme: Option<&'a Theme>,
     pub width: f32,
     pub height: f32,
 }
@@ -23,30 +185,132 @@ impl<'a> CalcKeyboard<'a> {
     pub fn from_buffer(buffer: &'a mut math_exp::MathExp) -> Self {
         Self {
             buffer,
-            width: 340.0,
+            layout: None,
+            theme: None,
+            width: 322.0,
             height: 320.0,
         }
     }
 
-    pub fn show(self, ui: &mut egui::Ui) {
-        egui::Grid::new("keyboard")
-            .num_columns(5)
-            .max_col_width(self.width)
-            .show(ui, |ui| {
-                for (ind, title) in KEYS.iter().enumerate() {
-                    if ind % 5 == 0 && ind != 0 {
-                        ui.end_row();
+    /// Задать размер клавиатуры. Клавиши делят его поровну между столбцами и строками.
+    pub fn size(mut self, width: f32, height: f32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// Задать тему, которая определяет цвета клавиш. По умолчанию цвета берутся из egui.
+    pub fn theme(mut self, theme: &'a Theme) -> Self {
+        self.theme = Some(theme);
+        self
+    }
+
+    /// Задать раскладку. По умолчанию используется обычная.
+    pub fn layout(mut self, layout: &'a KeyboardLayout) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    /// Выполнить действие клавиши.
+    fn run(&mut self, action: KeyAction, modifiers: &mut Modifiers) {
+        match action {
+            KeyAction::Insert(s) => {
+                self.buffer.add(modifiers.apply(&s));
+                // Модификаторы действуют только на одно нажатие.
+                *modifiers = Modifiers::default();
+            }
+            KeyAction::Clear => { self.buffer.clear(); }
+            KeyAction::Backspace => { self.buffer.pop(); }
+            KeyAction::Evaluate => { self.buffer.calculate(); }
+            KeyAction::ToggleAngleMode => { self.buffer.toggle_angle_mode(); }
+            KeyAction::ToggleInv => { modifiers.inv = !modifiers.inv; }
+            KeyAction::ToggleHyp => { modifiers.hyp = !modifiers.hyp; }
+            KeyAction::MemoryClear(reg) => { self.buffer.memory_clear(reg); }
+            KeyAction::MemoryRecall(reg) => { self.buffer.memory_recall(reg); }
+            KeyAction::MemoryAdd(reg) => { self.buffer.memory_add(reg); }
+            KeyAction::MemorySub(reg) => { self.buffer.memory_sub(reg); }
+            KeyAction::MemoryStore(reg) => { self.buffer.memory_store(reg); }
+        }
+    }
+
+    pub fn show(mut self, ui: &mut egui::Ui) {
+        let modifiers_id = egui::Id::new("keyboard_modifiers");
+        let mut modifiers: Modifiers = ui.data_mut(|d| *d.get_temp_mut_or_default(modifiers_id));
+        let input_id = egui::Id::new("keyboard_input");
+        let mut input: HardwareInput = ui.data_mut(|d| d.get_temp_mut_or_default::<HardwareInput>(input_id).clone());
+        let time = ui.input(|i| i.time);
+        // Физическая клавиатура вводит выражение, только если ее не использует другой виджет.
+        if ui.memory(|m| m.focus().is_none()) {
+            input.handle(ui.ctx(), self.buffer, time);
+        }
+        if !input.pending.is_empty() {
+            ui.weak(i18n::trf(self.buffer.language(), i18n::Msg::Input, &[&input.pending]));
+        }
+        let default_layout;
+        let layout = match self.layout {
+            Some(layout) => { layout }
+            None => {
+                default_layout = KeyboardLayout::default();
+                &default_layout
+            }
+        };
+        // Ширина одного столбца. Клавиша, занимающая несколько столбцов,
+        // включает в себя и промежутки между ними.
+        let spacing = ui.spacing().item_spacing.x;
+        let columns = layout.columns as f32;
+        let column_width = (self.width - spacing * (columns - 1.0)) / columns;
+        let rows = layout.rows.len() as f32;
+        let row_spacing = ui.spacing().item_spacing.y;
+        let row_height = ((self.height - row_spacing * (rows - 1.0)) / rows).max(24.0);
+        let format = self.buffer.number_format();
+        for row in &layout.rows {
+            ui.horizontal(|ui| {
+                for key in row {
+                    let action = match key.action() {
+                        // Запятая раскладки разделяет аргументы и при десятичной запятой становится `;`.
+                        KeyAction::Insert(s) if s == "," => { KeyAction::Insert(format.separator().to_string()) }
+                        action => { action }
+                    };
+                    let title = modifiers.apply(&key.label);
+                    let selected = match action {
+                        KeyAction::ToggleInv => { modifiers.inv }
+                        KeyAction::ToggleHyp => { modifiers.hyp }
+                        _ => { input.is_flashed(title, time) }
+                    };
+                    let span = key.span as f32;
+                    // Подпись функции показывается в нотации выбранного языка.
+                    let label = match token::Func::try_from(title) {
+                        Ok(func) => { func.name(self.buffer.language()) }
+                        Err(_) => { format.key_label(title) }
+                    };
+                    let mut custom_key = CustomKey::from(label)
+                        .size(column_width * span + spacing * (span - 1.0), row_height);
+                    if let Some(theme) = self.theme {
+                        custom_key = custom_key.colors(theme.key_colors(KeyCategory::of(&action)));
                     }
-                    if CustomKey::from(*title).ui(ui).clicked() {
-                        match *title {
-                            "C" => { self.buffer.clear(); }
-                            "<=" => { self.buffer.pop(); }
-                            "=" => { self.buffer.calculate(); }
-                            _ => { self.buffer.add(title); }
-                        }
+                    // Клавиши, нажатие которых будет отклонено правилами ввода, недоступны.
+                    let enabled = match &action {
+                        KeyAction::Insert(s) => { self.buffer.can_accept(modifiers.apply(s)) }
+                        KeyAction::MemoryRecall(_) => { self.buffer.can_accept_operand() }
+                        _ => { true }
                     };
+                    if custom_key.selected(selected).enabled(enabled).ui(ui).clicked() {
+                        input.flush(self.buffer, time);
+                        self.run(action, &mut modifiers);
+                    }
                 }
             });
+        }
+        if let Some((_, start)) = input.flash {
+            // Перерисовываем, чтобы подсветка погасла вовремя.
+            if time - start < FLASH_DURATION {
+                ui.ctx().request_repaint_after(std::time::Duration::from_secs_f64(FLASH_DURATION));
+            }
+        }
+        ui.data_mut(|d| {
+            d.insert_temp(modifiers_id, modifiers);
+            d.insert_temp(input_id, input);
+        });
     }
 }
 
@@ -54,6 +318,12 @@ pub struct CustomKey {
     pub text: String,
     pub width: f32,
     pub height: f32,
+    /// Кнопка выделена (например, включенный модификатор).
+    pub selected: bool,
+    /// Кнопку можно нажать.
+    pub enabled: bool,
+    /// Цвета кнопки. Если не заданы, используются цвета egui.
+    pub colors: Option<KeyColors>,
 }
 
 impl CustomKey {
@@ -64,10 +334,26 @@ impl CustomKey {
             ..Default::default()
         }
     }
+    /// Выделить кнопку.
+    pub fn selected(mut self, selected: bool) -> Self {
+        self.selected = selected;
+        self
+    }
+    /// Задать цвета кнопки.
+    pub fn colors(mut self, colors: KeyColors) -> Self {
+        self.colors = Some(colors);
+        self
+    }
+    /// Сделать кнопку доступной или недоступной для нажатия.
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
     /// Задать размер кнопке.
-    fn _set_size(mut self, width: f32, height: f32) {
+    pub fn size(mut self, width: f32, height: f32) -> Self {
         self.width = width;
         self.height = height;
+        self
     }
 }
 
@@ -77,6 +363,9 @@ impl Default for CustomKey {
             text: "".to_string(),
             width: 58.0,
             height: 48.0,
+            selected: false,
+            enabled: true,
+            colors: None,
         }
     }
 }
@@ -84,10 +373,131 @@ impl Default for CustomKey {
 
 impl Widget for CustomKey {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
-        ui.add_sized(
-            [self.width, self.height],
-            egui::Button::new(self.text).small(),
-        )
+        // Размер подписи растет вместе с кнопкой.
+        let font_size = (self.height * 0.35).clamp(12.0, 28.0);
+        let mut text = egui::RichText::new(self.text).size(font_size);
+        if let Some(colors) = self.colors {
+            text = text.color(colors.text);
+        }
+        let mut button = egui::Button::new(text);
+        if self.selected {
+            button = button.fill(ui.visuals().selection.bg_fill);
+        } else if let Some(colors) = self.colors {
+            button = button.fill(colors.fill);
+        }
+        ui.add_enabled_ui(self.enabled, |ui| ui.add_sized([self.width, self.height], button)).inner
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::NumberFormat;
+    use crate::i18n::Language;
+    use crate::math_exp::MathExp;
+
+    fn key(key: egui::Key) -> egui::Event {
+        egui::Event::Key { key, pressed: true, repeat: false, modifiers: egui::Modifiers::NONE }
+    }
+
+    fn text(s: &str) -> egui::Event {
+        egui::Event::Text(s.to_string())
+    }
+
+    /// Передать события физической клавиатуры в одном кадре.
+    fn press(input: &mut HardwareInput, m: &mut MathExp, events: Vec<egui::Event>) {
+        let ctx = egui::Context::default();
+        let raw = egui::RawInput { events, ..Default::default() };
+        let _ = ctx.run(raw, |ctx| input.handle(ctx, m, 0.0));
+    }
+
+    #[test]
+    fn enter_calculates() {
+        let (mut input, mut m) = (HardwareInput::default(), MathExp::new());
+        press(&mut input, &mut m, vec![text("12+3"), key(egui::Key::Enter)]);
+        assert_eq!(m.get_output(), "15");
+        assert!(input.is_flashed("=", 0.0));
+        assert!(!input.is_flashed("=", FLASH_DURATION));
+        // Знак равенства вычисляет выражение так же, как Enter.
+        press(&mut input, &mut m, vec![text("2*4=")]);
+        assert_eq!(m.get_output(), "8");
+    }
+
+    #[test]
+    fn backspace_and_escape() {
+        let (mut input, mut m) = (HardwareInput::default(), MathExp::new());
+        press(&mut input, &mut m, vec![text("12+"), key(egui::Key::Backspace)]);
+        assert_eq!(m.to_string(), "12");
+        assert!(input.is_flashed("<=", 0.0));
+        // Сначала стирается набираемое имя, а выражение остается.
+        press(&mut input, &mut m, vec![text("+si"), key(egui::Key::Backspace)]);
+        assert_eq!(input.pending, "s");
+        assert_eq!(m.to_string(), "12+");
+        press(&mut input, &mut m, vec![key(egui::Key::Escape)]);
+        assert!(input.pending.is_empty());
+        assert_eq!(m.to_string(), "");
+        assert!(input.is_flashed("C", 0.0));
+    }
+
+    #[test]
+    fn characters_map_to_keys() {
+        let (mut input, mut m) = (HardwareInput::default(), MathExp::new());
+        press(&mut input, &mut m, vec![text("7*")]);
+        assert!(input.is_flashed("*", 0.0));
+        press(&mut input, &mut m, vec![text("1.5")]);
+        assert!(input.is_flashed("5", 0.0));
+        press(&mut input, &mut m, vec![text(".")]);
+        assert!(!input.is_flashed(".", 0.0));
+        assert_eq!(m.to_string(), "7*1.5");
+        // Отклоненный символ не подсвечивает клавишу.
+        press(&mut input, &mut m, vec![text(")")]);
+        assert!(!input.is_flashed(")", 0.0));
+
+        // При десятичной запятой запятая подсвечивает клавишу разделителя,
+        // а точка с запятой - клавишу разделителя аргументов.
+        let (mut input, mut m) = (HardwareInput::default(), MathExp::new());
+        m.set_number_format(NumberFormat::for_language(Language::Ru));
+        press(&mut input, &mut m, vec![text("max(1,")]);
+        assert!(input.is_flashed(".", 0.0));
+        press(&mut input, &mut m, vec![text("5;")]);
+        assert!(input.is_flashed(",", 0.0));
+        assert_eq!(m.to_string(), "max(1,5;");
+    }
+
+    #[test]
+    fn letters_type_names() {
+        let (mut input, mut m) = (HardwareInput::default(), MathExp::new());
+        press(&mut input, &mut m, vec![text("tan")]);
+        assert_eq!(input.pending, "tan");
+        assert!(m.tokens().is_empty());
+        // Скобка добавляет функцию вместе с открывающейся скобкой.
+        press(&mut input, &mut m, vec![text("(pi")]);
+        assert_eq!(m.tokens(), [token::Token::Function(token::Func::Tg), token::Token::Operation(token::Op::ParenLeft)]);
+        assert_eq!(input.pending, "pi");
+        press(&mut input, &mut m, vec![text(")+log2(8)")]);
+        assert_eq!(m.to_string(), "tg(π)+log2(8)");
+        assert!(input.pending.is_empty());
+
+        // Подсвечивается клавиша с отображаемым именем функции.
+        let (mut input, mut m) = (HardwareInput::default(), MathExp::new());
+        press(&mut input, &mut m, vec![text("atan(")]);
+        assert!(input.is_flashed("arctg", 0.0));
+    }
+
+    #[test]
+    fn unknown_function_stays_pending() {
+        let (mut input, mut m) = (HardwareInput::default(), MathExp::new());
+        press(&mut input, &mut m, vec![text("sinn(")]);
+        assert_eq!(input.pending, "sinn");
+        assert!(m.tokens().is_empty());
+        // Опечатку можно исправить и продолжить ввод.
+        press(&mut input, &mut m, vec![key(egui::Key::Backspace), text("(0)")]);
+        assert_eq!(m.to_string(), "sin(0)");
+
+        // Имя переменной и знак равенства в начале выражения - присваивание.
+        let (mut input, mut m) = (HardwareInput::default(), MathExp::new());
+        press(&mut input, &mut m, vec![text("x=5"), key(egui::Key::Enter)]);
+        assert_eq!(m.env().get("x"), Some(5.0));
+    }
+}
@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+
+use crate::i18n;
+
+
+/// Действие, которое выполняет клавиша.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyAction {
+    /// Добавить токен в выражение (см. [`crate::math_exp::MathExp::add`]).
+    Insert(String),
+    /// Очистить выражение.
+    Clear,
+    /// Удалить последний символ или токен.
+    Backspace,
+    /// Вычислить выражение.
+    Evaluate,
+    /// Переключить единицы измерения углов.
+    ToggleAngleMode,
+    /// Переключить модификатор обратной функции.
+    ToggleInv,
+    /// Переключить модификатор гиперболической функции.
+    ToggleHyp,
+    // Операции с регистром памяти с указанным номером.
+    MemoryClear(usize),
+    MemoryRecall(usize),
+    MemoryAdd(usize),
+    MemorySub(usize),
+    MemoryStore(usize),
+}
+
+fn default_span() -> usize {
+    1
+}
+
+/// Клавиша раскладки.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Key {
+    /// Подпись клавиши.
+    pub label: String,
+    /// Действие клавиши. Если не задано, то клавиша вставляет свою подпись.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<KeyAction>,
+    /// Количество столбцов, которое занимает клавиша.
+    #[serde(default = "default_span")]
+    pub span: usize,
+}
+
+impl Key {
+    /// Действие клавиши с учетом значения по умолчанию.
+    pub fn action(&self) -> KeyAction {
+        self.action.clone().unwrap_or_else(|| KeyAction::Insert(self.label.clone()))
+    }
+}
+
+/// Ошибка загрузки раскладки.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutError {
+    /// Не удалось прочитать файл.
+    Io(String),
+    /// Не удалось разобрать JSON или TOML.
+    Parse(String),
+    /// Формат файла не поддерживается (по расширению).
+    UnknownFormat(String),
+    /// Раскладка пустая.
+    Empty,
+    /// Строка с указанным номером шире раскладки или содержит клавишу нулевой ширины.
+    InvalidRow(usize),
+}
+
+impl LayoutError {
+    /// Сообщение об ошибке на языке `lang`.
+    pub fn localized(&self, lang: i18n::Language) -> String {
+        match self {
+            LayoutError::Io(e) => { i18n::trf(lang, i18n::Msg::LayoutIo, &[e]) }
+            LayoutError::Parse(e) => { i18n::trf(lang, i18n::Msg::LayoutParse, &[e]) }
+            LayoutError::UnknownFormat(ext) => { i18n::trf(lang, i18n::Msg::LayoutUnknownFormat, &[ext]) }
+            LayoutError::Empty => { i18n::tr(lang, i18n::Msg::LayoutEmpty).to_string() }
+            LayoutError::InvalidRow(row) => { i18n::trf(lang, i18n::Msg::LayoutInvalidRow, &[&(row + 1)]) }
+        }
+    }
+}
+
+impl std::fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.localized(i18n::Language::En))
+    }
+}
+
+impl std::error::Error for LayoutError {}
+
+/// Раскладка клавиатуры калькулятора: строки клавиш в сетке из `columns` столбцов.
+///
+/// Встроенные раскладки описаны в файлах `src/layouts/*`, пользовательские
+/// можно загрузить из JSON или TOML того же формата.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeyboardLayout {
+    /// Имя, по которому раскладка выбирается и сохраняется в настройках.
+    pub name: String,
+    /// Название для интерфейса.
+    #[serde(default)]
+    pub title: String,
+    pub columns: usize,
+    pub rows: Vec<Vec<Key>>,
+    /// Имя раскладки, которая показывается вместо этой в альбомной ориентации.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub landscape: Option<String>,
+}
+
+impl KeyboardLayout {
+    /// Обычный калькулятор: цифры и арифметические операции.
+    pub fn basic() -> Self {
+        Self::from_toml(include_str!("layouts/basic.toml")).expect("встроенная раскладка basic")
+    }
+
+    /// Инженерный калькулятор: функции, константы, память.
+    pub fn scientific() -> Self {
+        Self::from_json(include_str!("layouts/scientific.json")).expect("встроенная раскладка scientific")
+    }
+
+    /// Все встроенные раскладки. Первая используется по умолчанию: в книжной ориентации
+    /// это обычная раскладка, а в альбомной она заменяется инженерной.
+    pub fn builtin() -> Vec<Self> {
+        vec![Self::basic(), Self::scientific()]
+    }
+
+    /// Разобрать раскладку из JSON.
+    pub fn from_json(s: &str) -> Result<Self, LayoutError> {
+        let layout: Self = serde_json::from_str(s).map_err(|e| LayoutError::Parse(e.to_string()))?;
+        layout.validate()
+    }
+
+    /// Разобрать раскладку из TOML.
+    pub fn from_toml(s: &str) -> Result<Self, LayoutError> {
+        let layout: Self = toml::from_str(s).map_err(|e| LayoutError::Parse(e.to_string()))?;
+        layout.validate()
+    }
+
+    /// Разобрать раскладку из JSON или TOML, например, вставленную пользователем.
+    /// Описание в формате JSON начинается с `{`.
+    pub fn parse(s: &str) -> Result<Self, LayoutError> {
+        if s.trim_start().starts_with('{') { Self::from_json(s) } else { Self::from_toml(s) }
+    }
+
+    /// Разобрать содержимое файла `file_name`, формат определяется по расширению `.json` или `.toml`.
+    pub fn from_file(file_name: &str, s: &str) -> Result<Self, LayoutError> {
+        match std::path::Path::new(file_name).extension().and_then(|ext| ext.to_str()) {
+            Some("json") => { Self::from_json(s) }
+            Some("toml") => { Self::from_toml(s) }
+            ext => { Err(LayoutError::UnknownFormat(ext.unwrap_or_default().to_string())) }
+        }
+    }
+
+    /// Раскладка, которая показывается вместо этой: в альбомной ориентации
+    /// это раскладка [`KeyboardLayout::landscape`], если она есть среди `layouts`.
+    pub fn displayed<'a>(&'a self, layouts: &'a [KeyboardLayout], landscape: bool) -> &'a KeyboardLayout {
+        match &self.landscape {
+            Some(name) if landscape => { layouts.iter().find(|l| &l.name == name).unwrap_or(self) }
+            _ => { self }
+        }
+    }
+
+    /// Загрузить раскладку из файла `.json` или `.toml`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, LayoutError> {
+        let path = path.as_ref();
+        let s = std::fs::read_to_string(path).map_err(|e| LayoutError::Io(e.to_string()))?;
+        Self::from_file(&path.to_string_lossy(), &s)
+    }
+
+    /// Проверить, что раскладка не пустая и все строки помещаются в сетку.
+    /// Нужна для раскладок, полученных не через `from_*`, например, из сохраненного состояния.
+    pub fn validate(self) -> Result<Self, LayoutError> {
+        if self.columns == 0 || self.rows.iter().all(Vec::is_empty) {
+            return Err(LayoutError::Empty);
+        }
+        for (ind, row) in self.rows.iter().enumerate() {
+            let width: usize = row.iter().map(|key| key.span).sum();
+            if width > self.columns || row.iter().any(|key| key.span == 0) {
+                return Err(LayoutError::InvalidRow(ind));
+            }
+        }
+        Ok(self)
+    }
+}
+
+/// Первая из встроенных раскладок (см. [`KeyboardLayout::builtin`]).
+impl Default for KeyboardLayout {
+    fn default() -> Self {
+        Self::basic()
+    }
+}
@@ -1,3 +1,12 @@
 pub mod keyboard;
+pub mod layout;
 pub mod token;
 pub mod math_exp;
+pub mod error;
+pub mod expr;
+pub mod env;
+pub mod format;
+pub mod history;
+pub mod i18n;
+pub mod state;
+pub mod theme;
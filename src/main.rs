@@ -2,14 +2,27 @@
 
 use eframe::egui;
 
-use calculator_wasm_rust_pwa::{keyboard, math_exp};
+use calculator_wasm_rust_pwa::{history, i18n, keyboard, layout, math_exp, state, theme};
 
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> eframe::Result<()> {
+    // Дополнительные раскладки можно передать путями к файлам .json или .toml.
+    // Ошибки загрузки показываются и в окне загрузки раскладок.
+    let mut layouts = Vec::new();
+    let mut errors = Vec::new();
+    for path in std::env::args().skip(1) {
+        match layout::KeyboardLayout::load(&path) {
+            Ok(layout) => { layouts.push(layout); }
+            Err(e) => {
+                eprintln!("{}: {}", path, e.localized(i18n::Language::detect().unwrap_or_default()));
+                errors.push((path, e));
+            }
+        }
+    }
     eframe::run_native(
         "calculator-wasm-rust-pwa",
         eframe::NativeOptions::default(),
-        Box::new(|cc| Box::new(CalcApp::new(cc))),
+        Box::new(|cc| Box::new(CalcApp::new(cc, layouts, errors))),
     )
 }
 
@@ -21,7 +34,7 @@ fn main() {
         eframe::start_web(
             "calculator-wasm-rust-pwa",
             eframe::WebOptions::default(),
-            Box::new(|cc| Box::new(CalcApp::new(cc))),
+            Box::new(|cc| Box::new(CalcApp::new(cc, Vec::new(), Vec::new()))),
         )
             .await
             .expect("failed to start calculator-wasm-rust-pwa");
@@ -30,51 +43,320 @@ fn main() {
 
 struct CalcApp {
     math_exp: math_exp::MathExp,
+    /// Доступные раскладки клавиатуры: встроенные и загруженные пользователем.
+    layouts: Vec<layout::KeyboardLayout>,
+    /// Индекс выбранной раскладки.
+    layout: usize,
+    theme: theme::Theme,
+    /// Открыт ли редактор цветов темы.
+    theme_editor: bool,
+    layout_loader: LayoutLoader,
+}
+
+/// Окно загрузки пользовательской раскладки.
+#[derive(Default)]
+struct LayoutLoader {
+    open: bool,
+    /// Вставленное описание раскладки.
+    text: String,
+    /// Ошибки последней загрузки: имя файла (если раскладка загружалась из файла) и ошибка.
+    errors: Vec<(Option<String>, layout::LayoutError)>,
+    /// Имя последней загруженной раскладки.
+    loaded: Option<String>,
 }
 
 impl CalcApp {
-    fn new(_cc: &eframe::CreationContext<'_>) -> Self {
-        CalcApp {
-            math_exp: math_exp::MathExp::default(),
+    fn new(
+        cc: &eframe::CreationContext<'_>,
+        extra_layouts: Vec<layout::KeyboardLayout>,
+        errors: Vec<(String, layout::LayoutError)>,
+    ) -> Self {
+        // Восстанавливаем состояние предыдущего сеанса, если оно было сохранено.
+        let mut saved = cc.storage.and_then(state::CalcState::load).unwrap_or_default();
+        let mut app = CalcApp {
+            math_exp: math_exp::MathExp::new(),
+            layouts: layout::KeyboardLayout::builtin(),
+            layout: 0,
+            theme: saved.theme.clone(),
+            theme_editor: false,
+            layout_loader: LayoutLoader {
+                open: !errors.is_empty(),
+                errors: errors.into_iter().map(|(path, e)| (Some(path), e)).collect(),
+                ..LayoutLoader::default()
+            },
+        };
+        // Сохраненные раскладки проверяются заново, поврежденные пропускаются.
+        let custom = std::mem::take(&mut saved.custom_layouts).into_iter().filter_map(|l| l.validate().ok());
+        for layout in custom.chain(extra_layouts) {
+            app.add_layout(layout);
+        }
+        app.layout = app.layouts.iter().position(|l| l.name == saved.layout).unwrap_or_default();
+        app.math_exp = saved.restore();
+        app
+    }
+
+    /// Добавить раскладку. Раскладка с тем же именем заменяется.
+    /// Возвращает индекс добавленной раскладки.
+    fn add_layout(&mut self, layout: layout::KeyboardLayout) -> usize {
+        match self.layouts.iter().position(|l| l.name == layout.name) {
+            Some(ind) => {
+                self.layouts[ind] = layout;
+                ind
+            }
+            None => {
+                self.layouts.push(layout);
+                self.layouts.len() - 1
+            }
+        }
+    }
+
+    /// Индекс следующей раскладки, которая выглядит иначе, чем текущая.
+    /// Раскладки, которые в этой ориентации заменяются текущей, пропускаются.
+    fn next_layout(&self, landscape: bool) -> usize {
+        let current = &self.layouts[self.layout].displayed(&self.layouts, landscape).name;
+        (1..self.layouts.len())
+            .map(|step| (self.layout + step) % self.layouts.len())
+            .find(|&ind| &self.layouts[ind].displayed(&self.layouts, landscape).name != current)
+            .unwrap_or(self.layout)
+    }
+
+    /// Выбрать загруженную раскладку или запомнить ошибку загрузки из файла `source`.
+    fn load_layout(&mut self, source: Option<String>, result: Result<layout::KeyboardLayout, layout::LayoutError>) {
+        match result {
+            Ok(layout) => {
+                self.layout_loader.loaded = Some(layout.name.clone());
+                self.layout = self.add_layout(layout);
+            }
+            Err(e) => { self.layout_loader.errors.push((source, e)); }
+        }
+    }
+
+    /// Загрузить раскладки из файлов, перетащенных в окно приложения.
+    fn load_dropped_layouts(&mut self, ctx: &egui::Context) {
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        if dropped.is_empty() { return; }
+        self.layout_loader.open = true;
+        self.layout_loader.errors.clear();
+        self.layout_loader.loaded = None;
+        for file in dropped {
+            // В нативном приложении известен путь к файлу, в браузере - его имя и содержимое.
+            let (source, result) = match (file.path, file.bytes) {
+                #[cfg(not(target_arch = "wasm32"))]
+                (Some(path), _) => { (Some(path.display().to_string()), layout::KeyboardLayout::load(&path)) }
+                (_, Some(bytes)) => {
+                    let result = std::str::from_utf8(&bytes)
+                        .map_err(|e| layout::LayoutError::Io(e.to_string()))
+                        .and_then(|s| layout::KeyboardLayout::from_file(&file.name, s));
+                    (Some(file.name), result)
+                }
+                _ => { continue; }
+            };
+            self.load_layout(source, result);
+        }
+    }
+
+    /// Окно загрузки раскладки: вставленный текст и результат последней загрузки.
+    fn layout_loader_window(&mut self, ctx: &egui::Context, lang: i18n::Language) {
+        let loaded = self.layout_loader.loaded.as_ref()
+            .and_then(|name| self.layouts.iter().find(|l| &l.name == name))
+            .map(|l| layout_title(l, lang).to_string());
+        let loader = &mut self.layout_loader;
+        let error_color = self.theme.error;
+        let mut pasted = None;
+        egui::Window::new(i18n::tr(lang, i18n::Msg::LoadLayout))
+            .open(&mut loader.open)
+            .show(ctx, |ui| {
+                ui.label(i18n::tr(lang, i18n::Msg::LoadLayoutHint));
+                egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                    ui.add(egui::TextEdit::multiline(&mut loader.text).code_editor().desired_width(f32::INFINITY));
+                });
+                if ui.add_enabled(!loader.text.trim().is_empty(), egui::Button::new(i18n::tr(lang, i18n::Msg::Load))).clicked() {
+                    pasted = Some(layout::KeyboardLayout::parse(&loader.text));
+                }
+                if let Some(title) = &loaded {
+                    ui.label(i18n::trf(lang, i18n::Msg::LayoutLoaded, &[title]));
+                }
+                for (source, e) in &loader.errors {
+                    let text = match source {
+                        Some(source) => { format!("{}: {}", source, e.localized(lang)) }
+                        None => { e.localized(lang) }
+                    };
+                    ui.colored_label(error_color, text);
+                }
+            });
+        if let Some(result) = pasted {
+            self.layout_loader.errors.clear();
+            self.layout_loader.loaded = None;
+            self.load_layout(None, result);
         }
     }
 }
 
 impl eframe::App for CalcApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        let mut saved = state::CalcState::capture(&self.math_exp);
+        saved.layout = self.layouts[self.layout].name.clone();
+        saved.theme = self.theme.clone();
+        let builtin = layout::KeyboardLayout::builtin();
+        saved.custom_layouts = self.layouts.iter().filter(|l| !builtin.contains(l)).cloned().collect();
+        saved.save(storage);
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        egui::TopBottomPanel::top("screen_panel").show(ctx, |ui| {
-            let size_font = |l: f32| -> f32 {
-                if l <= 22.0 {
-                    25.0
-                } else {
-                    let a = 330.0 / (l / 0.6);
-                    if a > 12.0 { a } else { 12.0 }
+        // Все размеры задаются в точках egui, поэтому масштаб экрана
+        // (pixels_per_point) учитывается автоматически.
+        let screen = ctx.screen_rect();
+        let landscape = screen.width() > screen.height();
+        // Высота строк экрана калькулятора пропорциональна высоте окна.
+        let expression_height = (screen.height() * 0.12).clamp(40.0, 140.0);
+        let result_height = (screen.height() * 0.08).clamp(30.0, 100.0);
+
+        let lang = self.math_exp.language();
+
+        self.theme.apply(ctx);
+        egui::Window::new(i18n::tr(lang, i18n::Msg::Theme))
+            .open(&mut self.theme_editor)
+            .resizable(false)
+            .show(ctx, |ui| self.theme.editor(ui, lang));
+        self.load_dropped_layouts(ctx);
+        self.layout_loader_window(ctx, lang);
+
+        let screen_frame = egui::Frame::side_top_panel(&ctx.style()).fill(self.theme.display);
+        egui::TopBottomPanel::top("screen_panel").frame(screen_frame).show(ctx, |ui| {
+            let width = ui.available_width();
+
+            // Строка состояния: режимы, которые влияют на вычисления.
+            ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new(self.math_exp.angle_mode().to_string())
+                        .font(egui::FontId::monospace(12.0))
+                        .weak(),
+                );
+                if self.math_exp.has_memory() {
+                    ui.label(egui::RichText::new("M").font(egui::FontId::monospace(12.0)));
                 }
-            };
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.menu_button("🌐", |ui| {
+                        for language in i18n::Language::ALL {
+                            if ui.selectable_label(lang == language, language.title()).clicked() {
+                                self.math_exp.set_language(language);
+                                ui.close_menu();
+                            }
+                        }
+                    }).response.on_hover_text(i18n::tr(lang, i18n::Msg::Language));
+                    ui.menu_button("🎨", |ui| {
+                        for preset in theme::Theme::presets() {
+                            let selected = self.theme.name == preset.name;
+                            if ui.selectable_label(selected, preset.title(lang)).clicked() {
+                                self.theme = preset;
+                                ui.close_menu();
+                            }
+                        }
+                        ui.separator();
+                        if ui.button(i18n::tr(lang, i18n::Msg::ThemeCustomize)).clicked() {
+                            self.theme_editor = true;
+                            ui.close_menu();
+                        }
+                    }).response.on_hover_text(i18n::trf(lang, i18n::Msg::ThemeHint, &[&self.theme.title(lang)]));
+                    ui.menu_button("🔢", |ui| {
+                        let mut format = self.math_exp.number_format();
+                        format.editor(ui, lang);
+                        self.math_exp.set_number_format(format);
+                    }).response.on_hover_text(i18n::tr(lang, i18n::Msg::NumberFormat));
+                    // Переключение раскладок по кругу. На кнопке название раскладки,
+                    // которая видна сейчас, с учетом замены в альбомной ориентации.
+                    let displayed = self.layouts[self.layout].displayed(&self.layouts, landscape);
+                    if ui.small_button(layout_title(displayed, lang))
+                        .on_hover_text(i18n::tr(lang, i18n::Msg::SwitchLayout))
+                        .clicked() {
+                        self.layout = self.next_layout(landscape);
+                    }
+                    if ui.small_button("⌨").on_hover_text(i18n::tr(lang, i18n::Msg::LoadLayout)).clicked() {
+                        self.layout_loader.open = true;
+                    }
+                    if let Some(ans) = self.math_exp.ans() {
+                        if ui.small_button("📋=").on_hover_text(i18n::tr(lang, i18n::Msg::CopyResult)).clicked() {
+                            ui.output_mut(|o| o.copied_text = self.math_exp.number_format().plain().format(ans));
+                        }
+                    }
+                    let has_expression = !self.math_exp.tokens().is_empty() || !self.math_exp.buffer().is_empty();
+                    if has_expression
+                        && ui.small_button("📋").on_hover_text(i18n::tr(lang, i18n::Msg::CopyExpression)).clicked() {
+                        ui.output_mut(|o| o.copied_text = self.math_exp.copy_text());
+                    }
+                });
+            });
 
             let result = self.math_exp.get_output();
             let result_length = result.chars().count() as f32;
             let expression = self.math_exp.to_string();
             let expression_length = expression.chars().count() as f32;
+            // Выражение собираем по токенам, чтобы подсветить токен, вызвавший ошибку.
+            let font_id = egui::FontId::monospace(keyboard::fit_font_size(width, expression_length, expression_height * 0.5));
+            let error_index = self.math_exp.get_error().map(|e| e.index());
+            let mut job = egui::text::LayoutJob::default();
+            for (ind, token) in self.math_exp.tokens().iter().enumerate() {
+                let color = if Some(ind) == error_index {
+                    self.theme.error
+                } else { self.theme.expression };
+                job.append(
+                    self.math_exp.token_text(token).as_str(),
+                    0.0,
+                    egui::TextFormat::simple(font_id.clone(), color),
+                );
+            }
+            job.append(
+                self.math_exp.buffer_text().as_str(),
+                0.0,
+                egui::TextFormat::simple(font_id.clone(), self.theme.expression),
+            );
+            // Вставленный текст, который не удалось разобрать.
+            if let Some(rejected) = self.math_exp.get_rejected() {
+                job.append(
+                    rejected.fragment.as_str(),
+                    0.0,
+                    egui::TextFormat::simple(font_id, self.theme.error),
+                );
+            }
             ui.add_sized(
-                [330.0, 70.0],
-                egui::Label::new(
-                    egui::RichText::new(expression)
-                        .font(egui::FontId::monospace(size_font(expression_length))),
-            ).wrap(true),
+                [width, expression_height],
+                egui::Label::new(job).wrap(true),
             );
             ui.add_sized(
-                [330.0, 45.0],
+                [width, result_height],
                 egui::Label::new(
                     egui::RichText::new(result)
-                        .font(egui::FontId::monospace(size_font(result_length)))
-                        .color(egui::Color32::LIGHT_GREEN)
+                        .font(egui::FontId::monospace(keyboard::fit_font_size(width, result_length, result_height * 0.6)))
+                        .color(self.theme.result)
                 ).wrap(true),
             );
         });
 
+        egui::TopBottomPanel::bottom("history_panel").show(ctx, |ui| {
+            history::HistoryPanel::from_buffer(&mut self.math_exp)
+                .theme(&self.theme)
+                .show(ui)
+        });
+
         egui::CentralPanel::default().show(ctx, |ui| {
-            keyboard::CalcKeyboard::from_buffer(&mut self.math_exp).show(ui)
+            // В альбомной ориентации раскладка может быть заменена более широкой.
+            let layout = self.layouts[self.layout].displayed(&self.layouts, landscape);
+            let size = ui.available_size();
+            keyboard::CalcKeyboard::from_buffer(&mut self.math_exp)
+                .layout(layout)
+                .theme(&self.theme)
+                .size(size.x, size.y)
+                .show(ui)
         });
     }
-}
\ No newline at end of file
+}
+
+/// Название раскладки: встроенные переводятся, у пользовательских берется название из файла.
+fn layout_title(layout: &layout::KeyboardLayout, lang: i18n::Language) -> &str {
+    match layout.name.as_str() {
+        "basic" => { i18n::tr(lang, i18n::Msg::LayoutBasic) }
+        "scientific" => { i18n::tr(lang, i18n::Msg::LayoutScientific) }
+        _ if layout.title.is_empty() => { &layout.name }
+        _ => { &layout.title }
+    }
+}
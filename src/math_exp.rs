@@ -1,11 +1,53 @@
+use crate::env::Env;
+use crate::error::{CalcError, ParseError};
+use crate::expr;
+use crate::format::NumberFormat;
+use crate::history::History;
+use crate::i18n;
 use crate::token;
 use crate::token::Weight;
 
 
+/// Количество регистров памяти: основной регистр M (с индексом 0) и регистры M1..M9.
+pub const MEMORY_REGISTERS: usize = 10;
+
+/// Строка, добавляемая в выражение (см. [`MathExp::add`]).
+enum Input {
+    /// Десятичный разделитель, дописывается в буфер.
+    Decimal,
+    /// Цифра, дописывается в буфер.
+    Digit,
+    /// Токен. Если `ans`, то перед ним вставляется результат предыдущего вычисления.
+    Token { token: token::Token, ans: bool },
+}
+
 pub struct MathExp {
     tokens: Vec<token::Token>,
     buffer: String,
     output: String,
+    /// Ошибка последнего вычисления. Сбрасывается при любом изменении выражения.
+    error: Option<CalcError>,
+    /// Фрагмент вставленного текста, который не удалось разобрать.
+    /// Сбрасывается при любом изменении выражения.
+    rejected: Option<ParseError>,
+    /// Единицы измерения углов для тригонометрических функций.
+    angle: token::AngleMode,
+    /// Переменные, заданные присваиванием.
+    env: Env,
+    /// Выражение только что вычислено, и его результат еще не использован.
+    /// Бинарная операция, введенная в этот момент, продолжает вычисление от результата.
+    fresh_result: bool,
+    /// Последняя бинарная операция вычисленного выражения и ее правый операнд.
+    /// Повторяется при повторном нажатии "=".
+    repeat: Option<(token::Op, f64)>,
+    /// Регистры памяти.
+    memory: [f64; MEMORY_REGISTERS],
+    /// История вычислений.
+    history: History,
+    /// Язык сообщений и имен функций.
+    lang: i18n::Language,
+    /// Формат вывода чисел. Определяет и десятичный разделитель при вводе.
+    format: NumberFormat,
 }
 
 impl Default for MathExp {
@@ -20,6 +62,16 @@ impl MathExp {
             tokens: Vec::new(),
             buffer: String::new(),
             output: String::new(),
+            error: None,
+            rejected: None,
+            angle: token::AngleMode::default(),
+            env: Env::new(),
+            fresh_result: false,
+            repeat: None,
+            memory: [0.0; MEMORY_REGISTERS],
+            history: History::new(),
+            lang: i18n::Language::default(),
+            format: NumberFormat::default(),
         }
     }
 
@@ -27,57 +79,288 @@ impl MathExp {
         self.output.clone()
     }
 
+    /// Ошибка последнего вычисления, если оно завершилось неудачей.
+    pub fn get_error(&self) -> Option<&CalcError> {
+        self.error.as_ref()
+    }
+
+    /// Фрагмент вставленного текста, который не удалось принять (см. [`MathExp::paste`]).
+    pub fn get_rejected(&self) -> Option<&ParseError> {
+        self.rejected.as_ref()
+    }
+
+    /// Язык сообщений и имен функций.
+    pub fn language(&self) -> i18n::Language {
+        self.lang
+    }
+
+    /// Задать язык сообщений и имен функций.
+    pub fn set_language(&mut self, lang: i18n::Language) {
+        self.lang = lang;
+    }
+
+    /// Формат вывода чисел.
+    pub fn number_format(&self) -> NumberFormat {
+        self.format
+    }
+
+    /// Задать формат вывода чисел.
+    ///
+    /// При десятичной запятой знак `,` вводит дробную часть числа,
+    /// а аргументы функций разделяются знаком `;`.
+    pub fn set_number_format(&mut self, format: NumberFormat) {
+        self.format = format;
+    }
+
+    /// Единицы измерения углов для тригонометрических функций.
+    pub fn angle_mode(&self) -> token::AngleMode {
+        self.angle
+    }
+
+    /// Задать единицы измерения углов.
+    pub fn set_angle_mode(&mut self, angle: token::AngleMode) {
+        self.angle = angle;
+    }
+
+    /// Переключить единицы измерения углов на следующие по кругу (DEG -> RAD -> GRAD).
+    pub fn toggle_angle_mode(&mut self) {
+        self.angle = self.angle.next();
+    }
+
+    /// Переменные, заданные присваиванием.
+    pub fn env(&self) -> &Env {
+        &self.env
+    }
+
+    /// Изменяемый доступ к переменным, например, чтобы задать их значения заранее.
+    pub fn env_mut(&mut self) -> &mut Env {
+        &mut self.env
+    }
+
+    /// Результат последнего успешного вычисления.
+    pub fn ans(&self) -> Option<f64> {
+        self.env.ans()
+    }
+
+    /// История вычислений.
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Изменяемый доступ к истории, например, чтобы очистить ее.
+    pub fn history_mut(&mut self) -> &mut History {
+        &mut self.history
+    }
+
+    /// Заменить выражение токенами `tokens`, например, из записи истории или сохраненного состояния.
+    ///
+    /// Токены добавляются по тем же правилам, что и при вводе (см. [`MathExp::add`]).
+    /// Если такую последовательность нельзя получить вводом, то выражение остается пустым
+    /// и возвращается `false`.
+    pub fn load_tokens(&mut self, tokens: Vec<token::Token>) -> bool {
+        self.clear();
+        let mut tokens = tokens.into_iter();
+        while let Some(t) = tokens.next() {
+            let valid = match &t {
+                token::Token::Operand(val) => { val.is_finite() }
+                // Имя должно разбираться обратно в ту же переменную, а не в функцию или константу.
+                token::Token::Variable(name) => { token::Token::try_from(name.as_str()).as_ref() == Ok(&t) }
+                _ => { true }
+            };
+            let accepted = if valid { self.accept_token(t) } else { None };
+            let Some(t) = accepted else {
+                self.tokens.clear();
+                return false;
+            };
+            let is_function = matches!(t, token::Token::Function(_));
+            self.tokens.push(t);
+            // Открывающаяся скобка вставляется вместе с функцией и должна следовать за ней.
+            if is_function {
+                if tokens.next() != Some(token::Token::Operation(token::Op::ParenLeft)) {
+                    self.tokens.clear();
+                    return false;
+                }
+                self.tokens.push(token::Token::Operation(token::Op::ParenLeft));
+            }
+        }
+        true
+    }
+
+    /// Вставить число в выражение по тем же правилам, что и ввод числа.
+    ///
+    /// Возвращает `false`, если число не может стоять в текущей позиции.
+    pub fn insert_operand(&mut self, val: f64) -> bool {
+        self.error = None;
+        self.rejected = None;
+        self.fresh_result = false;
+        self.pop_buffer();
+        self.push_to_token(token::Token::Operand(val))
+    }
+
+    /// Значение регистра памяти `reg` (0 - основной регистр M, 1..9 - регистры M1..M9).
+    pub fn memory(&self, reg: usize) -> f64 {
+        self.memory.get(reg).copied().unwrap_or_default()
+    }
+
+    /// Есть ли в памяти ненулевые значения.
+    pub fn has_memory(&self) -> bool {
+        self.memory.iter().any(|m| *m != 0.0)
+    }
+
+    /// Прибавить текущее значение к регистру памяти (M+).
+    pub fn memory_add(&mut self, reg: usize) {
+        if reg >= MEMORY_REGISTERS { return; }
+        if let Some(val) = self.memory_operand() { self.memory[reg] += val; }
+    }
+
+    /// Вычесть текущее значение из регистра памяти (M-).
+    pub fn memory_sub(&mut self, reg: usize) {
+        if reg >= MEMORY_REGISTERS { return; }
+        if let Some(val) = self.memory_operand() { self.memory[reg] -= val; }
+    }
+
+    /// Сохранить текущее значение в регистр памяти (MS).
+    pub fn memory_store(&mut self, reg: usize) {
+        if reg >= MEMORY_REGISTERS { return; }
+        if let Some(val) = self.memory_operand() { self.memory[reg] = val; }
+    }
+
+    /// Вставить значение регистра памяти в выражение как число (MR).
+    ///
+    /// Вставка выполняется по тем же правилам, что и ввод числа.
+    pub fn memory_recall(&mut self, reg: usize) -> bool {
+        if reg >= MEMORY_REGISTERS { return false; }
+        self.insert_operand(self.memory[reg])
+    }
+
+    /// Записать значение `val` в регистр памяти.
+    pub fn set_memory(&mut self, reg: usize, val: f64) {
+        if let Some(m) = self.memory.get_mut(reg) { *m = val; }
+    }
+
+    /// Очистить регистр памяти (MC).
+    pub fn memory_clear(&mut self, reg: usize) {
+        if reg >= MEMORY_REGISTERS { return; }
+        self.memory[reg] = 0.0;
+    }
+
+    /// Значение для операций с памятью.
+    ///
+    /// Если выражение не пустое, то используется его значение (для присваивания -
+    /// значение правой части), иначе результат последнего вычисления.
+    /// Выражение, вывод, история и переменные при этом не меняются.
+    /// Если выражение не удается вычислить, то значения нет.
+    fn memory_operand(&self) -> Option<f64> {
+        if self.tokens.is_empty() && self.buffer.is_empty() { return self.env.ans(); }
+        let mut tokens = self.tokens.clone();
+        if !self.buffer.is_empty() { tokens.push(token::Token::Operand(self.buffer.parse().ok()?)); }
+        let start = if self.assignment_target().is_some() { 2 } else { 0 };
+        self.eval_tokens(&tokens, start).ok()
+    }
+
+    /// Имя переменной, если выражение является присваиванием вида `x = ...`.
+    fn assignment_target(&self) -> Option<&str> {
+        match self.tokens.as_slice() {
+            [token::Token::Variable(name), token::Token::Operation(token::Op::Assign), ..] => { Some(name) }
+            _ => { None }
+        }
+    }
+
+    /// Токены выражения (без значения, которое еще находится в буфере).
+    pub fn tokens(&self) -> &[token::Token] {
+        &self.tokens
+    }
+
+    /// Значение, которое вводится в данный момент и еще не стало токеном.
+    pub fn buffer(&self) -> &str {
+        &self.buffer
+    }
+
     fn update_output(&mut self, s: &str) {
         self.output = s.to_string();
     }
 
-    /// Вставка токенов с учетом правил:
+    /// Проверка токена по правилам вставки:
     ///
     /// * После Операнда (число) обязательно должен следовать знак операции или знак закрывающейся скобки.
-    /// * После знака операции может быть только знак открывающейся скобки или Операнд (число).
-    fn push_to_token(&mut self, t: token::Token) {
-        fn push(tokens: &mut Vec<token::Token>, t: token::Token) {
-            if let token::Token::Function(_) = t {
-                tokens.push(t);
-                tokens.push(token::Token::Operation(token::Op::ParenLeft));
-            } else { tokens.push(t); }
-        }
+    /// * После знака операции может быть только знак открывающейся скобки, унарный знак или Операнд (число).
+    /// * Знак `+` или `-` там, где ожидается операнд, становится унарным.
+    ///
+    /// Значение в буфере считается уже добавленным числом.
+    /// Возвращает токен в том виде, в котором он будет добавлен, или `None`, если токен недопустим.
+    fn accept_token(&self, t: token::Token) -> Option<token::Token> {
         // Запрещаем вставлять закрывающуюся скобку,
         // если их количество после вставке будет превышать количество открывающихся скобок.
-        if let token::Token::Operation(token::Op::ParenRight) = t {
-            let mut count_paren: i32 = -1; // Устанавливаем в -1, т.к. в будущем мы хотим вставить одну скобку.
-            for token in &self.tokens {
-                if let token::Token::Operation(p) = token {
-                    match p {
-                        token::Op::ParenLeft => { count_paren += 1 }
-                        token::Op::ParenRight => { count_paren -= 1 }
-                        _ => {}
+        // Разделитель аргументов может стоять только внутри скобок вызова функции.
+        if let token::Token::Operation(token::Op::ParenRight | token::Op::Comma) = t {
+            // Открытые скобки: для каждой - является ли она скобкой вызова функции.
+            let mut open_parens: Vec<bool> = Vec::new();
+            for (ind, token) in self.tokens.iter().enumerate() {
+                match token {
+                    token::Token::Operation(token::Op::ParenLeft) => {
+                        let is_call = ind > 0 && matches!(self.tokens[ind - 1], token::Token::Function(_));
+                        open_parens.push(is_call);
                     }
+                    token::Token::Operation(token::Op::ParenRight) => { open_parens.pop(); }
+                    _ => {}
                 }
             }
-            if count_paren < 0 { return; }
+            let allow_insert = match t {
+                token::Token::Operation(token::Op::Comma) => { open_parens.last() == Some(&true) }
+                _ => { !open_parens.is_empty() }
+            };
+            if !allow_insert { return None; }
         }
 
+        let buffered = self.buffer.parse::<f64>().ok().map(token::Token::Operand);
+        let last_token = buffered.as_ref().or(self.tokens.last());
 
-        let last_token = self.tokens.last();
-        if last_token.is_none() {
+        // Присваивание допустимо только сразу после имени переменной в начале выражения.
+        if let token::Token::Operation(token::Op::Assign) = t {
+            let allow_insert = buffered.is_none() && matches!(self.tokens.as_slice(), [token::Token::Variable(_)]);
+            return if allow_insert { Some(t) } else { None };
+        }
+
+        // Знак сложения или вычитания в начале выражения, после открывающейся скобки
+        // или после другой операции не имеет левого операнда, поэтому он унарный.
+        let expects_operand = !matches!(
+            last_token,
+            Some(last) if last.is_operand() || matches!(last, token::Token::Operation(token::Op::ParenRight))
+        );
+        let t = match t {
+            token::Token::Operation(token::Op::Sub) if expects_operand => { token::Token::Operation(token::Op::Neg) }
+            token::Token::Operation(token::Op::Add) if expects_operand => { token::Token::Operation(token::Op::Pos) }
+            _ => { t }
+        };
+        // Унарный знак (например, из восстановленного выражения) допустим только там, где ожидается операнд.
+        if matches!(t, token::Token::Operation(token::Op::Neg | token::Op::Pos)) && !expects_operand {
+            return None;
+        }
+        // Токены, которые могут стоять перед операндом.
+        let is_prefix = matches!(
+            t,
+            token::Token::Operation(token::Op::ParenLeft | token::Op::Neg | token::Op::Pos)
+        );
+
+
+        let last_token = match last_token {
             // Когда список токенов пустой,
             // мы будем разрешать вставку новых токенов только
-            // если они не являются токенами операции (за исключение открывающейся скобки).
-            if !matches!(t,token::Token::Operation(_)) || matches!(t,token::Token::Operation(token::Op::ParenLeft)) {
-                push(&mut self.tokens, t);
+            // если они не являются токенами операции (за исключение открывающейся скобки и унарного знака).
+            None => {
+                return if !matches!(t, token::Token::Operation(_)) || is_prefix { Some(t) } else { None };
             }
-            return;
-        }
-        let last_token = last_token.unwrap();
+            Some(last_token) => { last_token }
+        };
 
 
         let allow_insert = match last_token {
-            // После числа:
-            token::Token::Operand(_) => {
+            // После числа или константы:
+            _ if last_token.is_operand() => {
                 match t {
                     // Запрещаем вставку функции, чисел или левой скобки после числа.
-                    token::Token::Function(_) | token::Token::Operand(_) | token::Token::Operation(token::Op::ParenLeft) => { false }
+                    _ if t.is_operand() => { false }
+                    token::Token::Function(_) | token::Token::Operation(token::Op::ParenLeft) => { false }
                     _ => { true }
                 }
             }
@@ -92,8 +375,8 @@ impl MathExp {
             // После операции кроме закрывающейся скобки:
             token::Token::Operation(_) => {
                 match t {
-                    token::Token::Operation(token::Op::ParenLeft) => { true }
-                    // Запрещаем вставку операций после операции (исключение открывающаяся скобка).
+                    _ if is_prefix => { true }
+                    // Запрещаем вставку операций после операции (исключение открывающаяся скобка и унарный знак).
                     token::Token::Operation(_) => { false }
                     _ => { true }
                 }
@@ -101,21 +384,84 @@ impl MathExp {
 
             _ => { true }
         };
+        if allow_insert { Some(t) } else { None }
+    }
 
-        if allow_insert {
-            push(&mut self.tokens, t);
-        } else {
-            self.update_output(format!("Токен {} не может быть добавлен после {}", t, last_token).as_str());
+    /// Вставка токена по правилам [`MathExp::accept_token`].
+    ///
+    /// Возвращает `false`, если токен не был добавлен.
+    fn push_to_token(&mut self, t: token::Token) -> bool {
+        match self.accept_token(t.clone()) {
+            Some(t) => {
+                let is_function = matches!(t, token::Token::Function(_));
+                self.tokens.push(t);
+                if is_function { self.tokens.push(token::Token::Operation(token::Op::ParenLeft)); }
+                true
+            }
+            None => {
+                if let Some(last_token) = self.tokens.last() {
+                    let message = i18n::trf(
+                        self.lang,
+                        i18n::Msg::TokenRejected,
+                        &[&t.localized(self.lang), &last_token.localized(self.lang)],
+                    );
+                    self.update_output(message.as_str());
+                }
+                false
+            }
         }
-        let mut s = String::new();
-        for token in &self.tokens {
-            s.push('[');
-            s.push_str(token.to_string().as_str());
-            s.push(']');
-            s.push(',');
+    }
+
+    /// Можно ли добавить в выражение число (например, из памяти или истории).
+    pub fn can_accept_operand(&self) -> bool {
+        self.accept_token(token::Token::Operand(0.0)).is_some()
+    }
+
+    /// Будет ли принята строка `s` методом [`MathExp::add`].
+    ///
+    /// Проверяет те же правила, что и `add`, но не меняет выражение.
+    pub fn can_accept(&self, s: &str) -> bool {
+        match self.classify(s) {
+            Some(Input::Decimal | Input::Digit) => { true }
+            Some(Input::Token { token, ans }) => { ans || self.accept_token(token).is_some() }
+            None => { false }
         }
     }
 
+    /// Разобрать строку, которая добавляется методом [`MathExp::add`].
+    ///
+    /// Разделитель и цифры проверяются здесь, а токен проверяется при вставке
+    /// ([`MathExp::accept_token`]), чтобы при отказе можно было сообщить причину.
+    /// Возвращает `None`, если строка не может быть добавлена.
+    fn classify(&self, s: &str) -> Option<Input> {
+        // Число не может следовать сразу за другим числом (константой) или закрывающейся скобкой.
+        let allow_number_input = !matches!(
+            self.tokens.last(),
+            Some(last) if last.is_operand() || matches!(last, token::Token::Operation(token::Op::ParenRight))
+        );
+        if self.format.is_decimal(s) {
+            // Мы не можем разрешить добавить больше чем одну точку.
+            // Десятичная запятая при этом не становится разделителем аргументов.
+            if allow_number_input && !self.buffer.contains('.') { Some(Input::Decimal) } else { None }
+        } else if s.parse::<u8>().is_ok() && allow_number_input {
+            Some(Input::Digit)
+        } else if let Ok(token) = token::Token::try_from(s) {
+            // Бинарная операция сразу после вычисления продолжает его от результата.
+            let ans = self.fresh_result && self.tokens.is_empty() && self.buffer.is_empty() && Self::is_binary(&token);
+            Some(Input::Token { token, ans })
+        } else { None }
+    }
+
+    /// Является ли токен бинарной операцией, которая может продолжить вычисление от результата.
+    fn is_binary(t: &token::Token) -> bool {
+        matches!(
+            t,
+            token::Token::Operation(
+                token::Op::Add | token::Op::Sub | token::Op::Multi | token::Op::Div | token::Op::Exp
+            )
+        )
+    }
+
     /// Удалить значение из буфера и поместить его в конец вектора с токенами.
     ///
     /// Будет выполнена попытка преобразовать значение хранящиеся в буфере.
@@ -123,21 +469,17 @@ impl MathExp {
     fn pop_buffer(&mut self) -> bool {
         if self.buffer.is_empty() { return true; }
         if let Ok(val) = self.buffer.parse::<f64>() {
-            if val.is_sign_negative() {
-                self.tokens.push(token::Token::Operation(token::Op::ParenLeft));
-                self.tokens.push(token::Token::Operand(val));
-                self.tokens.push(token::Token::Operation(token::Op::ParenRight));
-                self.buffer.clear();
-            } else {
-                self.tokens.push(token::Token::Operand(val));
-                self.buffer.clear();
-            }
+            self.tokens.push(token::Token::Operand(val));
+            self.buffer.clear();
             true
         } else { false }
     }
 
     /// Удалить последнее значение из вектора с токенами.
     pub fn pop(&mut self) {
+        self.error = None;
+        self.rejected = None;
+        self.fresh_result = false;
         if self.buffer.is_empty() {
             self.tokens.pop();
             // Если после удаленного токена стоял токен функции, то мы удаляем и его.
@@ -150,6 +492,9 @@ impl MathExp {
 
     /// Очистить буфера и вектор с токенами.
     pub fn clear(&mut self) {
+        self.error = None;
+        self.rejected = None;
+        self.fresh_result = false;
         self.buffer.clear();
         self.tokens.clear();
     }
@@ -161,104 +506,320 @@ impl MathExp {
     /// Если поступает последовательность символов, которая может интерпретироваться
     /// как операция или функция, то будет выполнена попытка выдавить текущее значение
     /// из буфера и только после будет выполнено добавление нового значения.
+    ///
+    /// Если сразу после вычисления вводится бинарная операция, то выражение
+    /// начинается с результата предыдущего вычисления (`Ans`), как на обычном калькуляторе.
+    ///
+    /// Десятичный разделитель (`.` или `,`, см. [`MathExp::set_number_format`])
+    /// хранится в буфере точкой.
+    ///
+    /// Возвращает `false`, если строка не была принята.
     pub fn add(&mut self, s: &str) -> bool {
-        let allow_number_input = !matches!(
-            self.tokens.last(),
-            Some(token::Token::Operation(token::Op::ParenRight))
-        );
-
-        if s == "." && allow_number_input {
-            if self.buffer.is_empty() {
-                self.buffer = "0.".to_string();
+        self.error = None;
+        self.rejected = None;
+        let input = self.classify(s);
+        self.fresh_result = false;
+        match input {
+            Some(Input::Decimal) => {
+                if self.buffer.is_empty() { self.buffer = "0.".to_string(); } else { self.buffer.push('.'); }
                 true
-            } else if self.buffer.contains('.') {
-                // мы не можем разрешить добавить больше чем одну точку.
-                false
-            } else {
-                self.buffer.push('.');
+            }
+            Some(Input::Digit) => {
+                self.buffer.push_str(s);
                 true
             }
-        } else if s.parse::<u8>().is_ok() && allow_number_input {
-            self.buffer.push_str(s);
-            true
-        } else if self.buffer.is_empty() && s == "-" && allow_number_input {
-            self.buffer = s.to_string();
-            true
-        } else if let Ok(t) = token::Token::try_from(s) {
-            self.pop_buffer();
-            self.push_to_token(t);
-            true
-        } else { false }
+            Some(Input::Token { token, ans }) => {
+                self.pop_buffer();
+                if ans { self.tokens.push(token::Token::Ans); }
+                self.push_to_token(token)
+            }
+            None => {
+                if s == "," && self.format.is_decimal(s) {
+                    self.update_output(i18n::tr(self.lang, i18n::Msg::DecimalCommaRejected));
+                }
+                false
+            }
+        }
     }
 
-    pub fn calculate(&mut self) {
-        self.pop_buffer();
-        let rpn = yard(&self.tokens);
-        match rpn {
-            Err(e) => { self.output = e }
-            Ok(tokens) => {
-                let mut stack: Vec<token::Token> = Vec::new();
-                for t in tokens {
-                    if stack.is_empty() {
-                        stack.push(t);
+    /// Создать выражение из строки произвольного вида, например `12.5*(3-sin(0.4))^2`.
+    ///
+    /// Пробелы между токенами игнорируются, числа могут состоять из нескольких цифр
+    /// и записываться в экспоненциальной форме (`1.5e3`), а функции записываются своими именами. Каждый токен добавляется по тем же правилам,
+    /// что и при вводе с клавиатуры (см. [`MathExp::add`]).
+    pub fn parse(s: &str) -> Result<Self, ParseError> {
+        let mut math_exp = Self::new();
+        math_exp.push_str(s)?;
+        Ok(math_exp)
+    }
+
+    /// Разобрать строку и добавить полученные токены в конец выражения.
+    ///
+    /// При ошибке возвращается позиция символа, который не удалось добавить.
+    /// Токены, добавленные до этой позиции, остаются в выражении.
+    pub fn push_str(&mut self, s: &str) -> Result<(), ParseError> {
+        let chars: Vec<char> = s.chars().collect();
+        let mut i = 0;
+        let format = self.format;
+        let is_number = |c: char| c.is_ascii_digit() || format.is_decimal(c.to_string().as_str());
+        // Разделитель разрядов (`1 234,5`) стоит после цифры целой части,
+        // и за ним следуют ровно три цифры.
+        let is_group = |after: &[char]| {
+            after.len() >= 3 && after[..3].iter().all(char::is_ascii_digit)
+                && !matches!(after.get(3), Some(c) if c.is_ascii_digit())
+        };
+        while i < chars.len() {
+            let c = chars[i];
+            if c.is_whitespace() {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            if is_number(c) {
+                // Число вводим посимвольно, чтобы сработали проверки из `add`.
+                // Два числа, разделенные пробелом, не должны склеиваться в одно.
+                if self.buffer.chars().any(|b| b.is_ascii_digit()) {
+                    return Err(ParseError { position: start, fragment: c.to_string() });
+                }
+                let mut grouped = false;
+                while i < chars.len() {
+                    // Разделитель разрядов проверяется раньше десятичного разделителя:
+                    // при десятичной запятой точка может разделять разряды (`1.234,5`).
+                    // Первая группа - от одной до трех цифр без ведущего нуля.
+                    // Внутри вызова функции запятая остается разделителем аргументов.
+                    let group = i > start
+                        && format.is_group_char(chars[i])
+                        && chars[i - 1].is_ascii_digit()
+                        && !self.buffer.contains('.')
+                        && (grouped || (self.buffer.len() <= 3 && !self.buffer.starts_with('0')))
+                        && is_group(&chars[i + 1..])
+                        && !(chars[i] == ',' && self.can_accept(","));
+                    if group {
+                        grouped = true;
+                    } else if is_number(chars[i]) {
+                        if !self.add(chars[i].to_string().as_str()) {
+                            return Err(ParseError { position: i, fragment: chars[i].to_string() });
+                        }
                     } else {
-                        match t {
-                            token::Token::Function(f) => {
-                                if let Some(token::Token::Operand(val)) = stack.pop() {
-                                    stack.push(match f {
-                                        token::Func::Sin => {
-                                            token::Token::Operand(val.sin())
-                                        }
-                                        token::Func::Cos => {
-                                            token::Token::Operand(val.cos())
-                                        }
-                                        token::Func::Tg => {
-                                            token::Token::Operand(val.sin() / val.cos())
-                                        }
-                                        token::Func::Ctg => {
-                                            token::Token::Operand(val.cos() / val.sin())
-                                        }
-                                        token::Func::Sqrt => {
-                                            token::Token::Operand(val.sqrt())
-                                        }
-                                    });
-                                } else {
-                                    self.output = "Ошибка вычисления".to_string()
-                                }
-                            }
-                            token::Token::Operation(op) => {
-                                if let Some(token::Token::Operand(second_val)) = stack.pop() {
-                                    if let Some(token::Token::Operand(first_val)) = stack.pop() {
-                                        if let Some(val) = match op {
-                                            token::Op::Add => {
-                                                Some(first_val + second_val)
-                                            }
-                                            token::Op::Sub => {
-                                                Some(first_val - second_val)
-                                            }
-                                            token::Op::Multi => {
-                                                Some(first_val * second_val)
-                                            }
-                                            token::Op::Div => {
-                                                Some(first_val / second_val)
-                                            }
-                                            token::Op::Exp => {
-                                                Some(first_val.powf(second_val))
-                                            }
-                                            _ => { None }
-                                        } { stack.push(token::Token::Operand(val)) }
-                                    } else { self.output = "Ошибка вычисления".to_string() }
-                                } else { self.output = "Ошибка вычисления".to_string() }
-                            }
-                            token::Token::Operand(_) => { stack.push(t); }
+                        break;
+                    }
+                    i += 1;
+                }
+                // Экспоненциальная запись (`1.5e3`, `6.02E+23`), как в выводе результата.
+                // Константа `e` после числа все равно недопустима, поэтому путаницы нет.
+                let len = exponent_len(&chars[i..]);
+                if len > 0 {
+                    let exponent: String = chars[i..i + len].iter().collect();
+                    let number = format!("{}{}", self.buffer.trim_end_matches('.'), exponent);
+                    match number.parse::<f64>() {
+                        Ok(val) if val.is_finite() => {
+                            self.buffer.clear();
+                            self.tokens.push(token::Token::Operand(val));
                         }
+                        _ => { return Err(ParseError { position: i, fragment: exponent }); }
                     }
+                    i += len;
                 }
-                self.output = if let Some(t) = stack.pop() {
-                    self.buffer.clear();
-                    self.tokens.clear();
-                    t.to_string()
-                } else { "Ошибка вычисления".to_string() };
+                continue;
+            }
+            let lexeme: String = if c.is_alphabetic() {
+                // Имя начинается с буквы и может содержать цифры (например, log2).
+                while i < chars.len() && chars[i].is_alphanumeric() { i += 1; }
+                chars[start..i].iter().collect()
+            } else {
+                i += 1;
+                c.to_string()
+            };
+            let is_function = token::Func::try_from(lexeme.as_str()).is_ok();
+            // Имя перед скобкой должно быть функцией: опечатка вроде `sinn(2)`
+            // не должна превратиться в переменную.
+            if c.is_alphabetic() && !is_function {
+                let next = chars[i..].iter().find(|c| !c.is_whitespace());
+                if next == Some(&'(') {
+                    return Err(ParseError { position: start, fragment: lexeme });
+                }
+            }
+            if !self.add(lexeme.as_str()) {
+                return Err(ParseError { position: start, fragment: lexeme });
+            }
+            if is_function {
+                // Открывающаяся скобка вставляется вместе с функцией,
+                // поэтому скобку из строки пропускаем.
+                let mut j = i;
+                while j < chars.len() && chars[j].is_whitespace() { j += 1; }
+                if j < chars.len() && chars[j] == '(' { i = j + 1; }
+            }
+        }
+        Ok(())
+    }
+
+    /// Вставить текст из буфера обмена в конец выражения.
+    ///
+    /// Текст разбирается так же, как в [`MathExp::push_str`]. Если часть текста
+    /// не удалось принять, то она запоминается для подсветки, а описание ошибки
+    /// записывается в вывод. Принятые до этого токены остаются в выражении.
+    pub fn paste(&mut self, s: &str) -> Result<(), ParseError> {
+        let result = self.push_str(s.trim());
+        if let Err(e) = &result {
+            self.output = e.localized(self.lang);
+            self.rejected = Some(e.clone());
+        }
+        result
+    }
+
+    /// Текст для копирования: выражение, если оно не пустое, иначе последний результат.
+    ///
+    /// Числа записываются без разделения разрядов, чтобы текст можно было вставить обратно.
+    pub fn copy_text(&self) -> String {
+        let plain = self.format.plain();
+        if self.tokens.is_empty() && self.buffer.is_empty() {
+            self.ans().map(|val| plain.format(val)).unwrap_or_default()
+        } else {
+            let mut o: String = self.tokens.iter().map(|t| t.formatted(self.lang, &plain)).collect();
+            o.push_str(self.buffer_text().as_str());
+            o
+        }
+    }
+
+    /// Токен выражения в том виде, в котором он показывается на экране.
+    ///
+    /// Числа выражения выводятся точно, округление применяется только к результату.
+    pub fn token_text(&self, t: &token::Token) -> String {
+        t.formatted(self.lang, &self.format.exact())
+    }
+
+    /// Значение в буфере с разделителями выбранного формата.
+    pub fn buffer_text(&self) -> String {
+        self.format.format_input(&self.buffer)
+    }
+
+    /// Вычислить значение выражения.
+    ///
+    /// Значение из буфера предварительно переносится в вектор с токенами,
+    /// само выражение при этом не очищается.
+    ///
+    /// Для присваивания `x = ...` вычисляется правая часть, и результат сохраняется в переменную.
+    pub fn evaluate(&mut self) -> Result<f64, CalcError> {
+        self.pop_buffer();
+        if let Some(name) = self.assignment_target().map(str::to_string) {
+            let val = self.eval_tokens(&self.tokens, 2)?;
+            self.env.set(name, val);
+            Ok(val)
+        } else { self.eval_tokens(&self.tokens, 0) }
+    }
+
+    /// Вычислить значение токенов `tokens`, начиная с индекса `start`.
+    fn eval_tokens(&self, tokens: &[token::Token], start: usize) -> Result<f64, CalcError> {
+        let rpn = yard(&tokens[start..]).map_err(|e| e.shift(start))?;
+        // Стек хранит значения вместе с индексами токенов, из которых они получены.
+        let mut stack: Vec<(usize, f64)> = Vec::new();
+        for RpnToken { index, token: t, argc } in rpn {
+            let ind = index + start;
+            match t {
+                token::Token::Operand(val) => { stack.push((ind, val)); }
+                token::Token::Constant(c) => { stack.push((ind, c.value())); }
+                token::Token::Variable(name) => {
+                    let val = self.env.get(&name).ok_or(CalcError::UndefinedVariable(ind))?;
+                    stack.push((ind, val));
+                }
+                token::Token::Ans => {
+                    let val = self.env.ans().ok_or(CalcError::UndefinedVariable(ind))?;
+                    stack.push((ind, val));
+                }
+                token::Token::Function(f) => {
+                    if !f.arity().accepts(argc) { return Err(CalcError::Arity(ind)); }
+                    if stack.len() < argc { return Err(CalcError::MissingOperand(ind)); }
+                    let args: Vec<f64> = stack
+                        .split_off(stack.len() - argc)
+                        .into_iter()
+                        .map(|(_, val)| val)
+                        .collect();
+                    stack.push((ind, f.apply(ind, &args, self.angle)?));
+                }
+                token::Token::Operation(op) if op.is_unary() => {
+                    let (_, val) = stack.pop().ok_or(CalcError::MissingOperand(ind))?;
+                    stack.push((ind, op.apply_unary(ind, val)?));
+                }
+                token::Token::Operation(op) => {
+                    let (_, second_val) = stack.pop().ok_or(CalcError::MissingOperand(ind))?;
+                    let (_, first_val) = stack.pop().ok_or(CalcError::MissingOperand(ind))?;
+                    stack.push((ind, op.apply(ind, first_val, second_val)?));
+                }
+            }
+        }
+        match stack.len() {
+            0 => { Err(CalcError::MissingOperand(start)) }
+            1 => { Ok(stack[0].1) }
+            // Лишние значения без операции между ними.
+            _ => { Err(CalcError::RejectedToken(stack[1].0)) }
+        }
+    }
+
+    /// Построить абстрактное синтаксическое дерево выражения.
+    ///
+    /// Значение из буфера предварительно переносится в вектор с токенами.
+    pub fn to_expr(&mut self) -> Result<expr::Expr, CalcError> {
+        self.pop_buffer();
+        expr::Expr::try_from(self.tokens.as_slice())
+    }
+
+    /// Операция верхнего уровня и значение ее правого операнда для выражения,
+    /// которое начинается с токена `start`, если это бинарная операция.
+    fn trailing_operation(&self, start: usize) -> Option<(token::Op, f64)> {
+        match expr::Expr::try_from(&self.tokens[start..]).ok()? {
+            expr::Expr::Binary(op, _, right, _) => { Some((op, right.eval_with(self.angle, &self.env).ok()?)) }
+            _ => { None }
+        }
+    }
+
+    /// Вычислить выражение и записать результат (или описание ошибки) в вывод.
+    ///
+    /// После успешного вычисления выражение очищается.
+    /// Повторное вычисление сразу после этого применяет последнюю операцию
+    /// с тем же операндом к результату: `5+3` и затем `=`, `=` дают 8, 11, 14.
+    pub fn calculate(&mut self) {
+        if self.fresh_result && self.tokens.is_empty() && self.buffer.is_empty() {
+            match self.repeat.clone() {
+                Some((op, val)) => {
+                    self.tokens = vec![
+                        token::Token::Ans,
+                        token::Token::Operation(op),
+                        token::Token::Operand(val),
+                    ];
+                }
+                // Повторять нечего, результат остается прежним.
+                None => { return; }
+            }
+        }
+        let target = self.assignment_target().map(str::to_string);
+        match self.evaluate() {
+            Ok(val) => {
+                self.repeat = self.trailing_operation(if target.is_some() { 2 } else { 0 });
+                // В историю `Ans` попадает своим значением, чтобы запись не менялась
+                // после следующих вычислений.
+                let ans = self.env.ans();
+                let tokens = std::mem::take(&mut self.tokens)
+                    .into_iter()
+                    .map(|t| match (t, ans) {
+                        (token::Token::Ans, Some(ans)) => { token::Token::Operand(ans) }
+                        (t, _) => { t }
+                    })
+                    .collect();
+                self.history.push(tokens, val);
+                self.buffer.clear();
+                self.env.set_ans(val);
+                self.fresh_result = true;
+                // Округленный результат помечается знаком "≈", точное значение остается в `Ans`.
+                let (result, rounded) = self.format.format_rounded(val);
+                self.output = match (target, rounded) {
+                    (Some(name), _) => { format!("{} {} {}", name, if rounded { "≈" } else { "=" }, result) }
+                    (None, true) => { format!("≈ {}", result) }
+                    (None, false) => { result }
+                };
+            }
+            Err(e) => {
+                self.output = e.localized(self.lang);
+                self.error = Some(e);
             }
         }
     }
@@ -268,9 +829,9 @@ impl std::fmt::Display for MathExp {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut o = String::new();
         for token in &self.tokens {
-            o.push_str(token.to_string().as_str());
+            o.push_str(self.token_text(token).as_str());
         }
-        o.push_str(self.buffer.as_str());
+        o.push_str(self.buffer_text().as_str());
         write!(
             f,
             "{}",
@@ -279,6 +840,22 @@ impl std::fmt::Display for MathExp {
     }
 }
 
+impl std::str::FromStr for MathExp {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+
+/// Длина показателя степени `e[+-]N` в начале `chars` или 0, если его там нет.
+fn exponent_len(chars: &[char]) -> usize {
+    if !matches!(chars.first(), Some('e' | 'E')) { return 0; }
+    let sign = matches!(chars.get(1), Some('+' | '-')) as usize;
+    let digits = chars[1 + sign..].iter().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 { 1 + sign + digits } else { 0 }
+}
 
 /// # Алгоритм сортировочной станции
 ///
@@ -296,57 +873,142 @@ impl std::fmt::Display for MathExp {
 /// нотацию используется 2 строки: входная и выходная, и стек для хранения операторов,
 /// ещё не добавленных в выходную очередь. При преобразовании алгоритм считывает 1 символ и
 /// производит действия, зависящие от данного символа.
-fn yard(input: &Vec<token::Token>) -> Result<Vec<token::Token>, String> {
-    let mut output: Vec<token::Token> = vec![];
-    let mut stack: Vec<token::Token> = vec![];
-    for token in input {
+///
+/// Каждый токен на выходе сопровождается своим индексом во входном векторе,
+/// а функции - еще и количеством переданных им аргументов.
+pub(crate) fn yard(input: &[token::Token]) -> Result<Vec<RpnToken>, CalcError> {
+    let mut output: Vec<RpnToken> = vec![];
+    let mut stack: Vec<(usize, token::Token)> = vec![];
+    // Количество разделителей аргументов внутри каждой открытой скобки.
+    let mut commas: Vec<usize> = vec![];
+    for (ind, token) in input.iter().enumerate() {
         match token {
-            token::Token::Operand(_o) => {
+            token::Token::Operand(_) | token::Token::Constant(_) | token::Token::Variable(_) | token::Token::Ans => {
                 // Если токен — число, то добавить его в очередь вывода.
-                output.push(token.clone())
+                output.push(RpnToken::new(ind, token.clone()))
             }
             token::Token::Function(_f) => {
-                stack.push(token.clone())
+                stack.push((ind, token.clone()))
             }
             token::Token::Operation(token::Op::ParenLeft) => {
-                stack.push(token.clone())
+                stack.push((ind, token.clone()));
+                commas.push(0);
             }
             token::Token::Operation(token::Op::ParenRight) => {
                 loop {
                     if let Some(last_token_in_stack) = stack.pop() {
                         match last_token_in_stack {
-                            token::Token::Operation(token::Op::ParenLeft) => {
+                            (paren_ind, token::Token::Operation(token::Op::ParenLeft)) => {
+                                let count = commas.pop().unwrap_or_default();
+                                // Если перед скобкой стоит функция, то скобка была ее аргументом.
+                                if let Some((_, token::Token::Function(_))) = stack.last() {
+                                    let (func_ind, func) = stack.pop().unwrap();
+                                    output.push(RpnToken { index: func_ind, token: func, argc: count + 1 });
+                                } else if count > 0 {
+                                    // Список значений через запятую допустим только как аргументы функции.
+                                    return Err(CalcError::RejectedToken(paren_ind));
+                                }
                                 break;
                             }
-                            _ => {
-                                output.push(last_token_in_stack.clone())
+                            (last_ind, last_token) => {
+                                output.push(RpnToken::new(last_ind, last_token))
                             }
                         }
                     } else {
-                        return Err("В выражении отсутствует скобка.".to_string());
+                        return Err(CalcError::UnbalancedParen(ind));
                     }
                 }
             }
-            token::Token::Operation(op1) => {
-                if let Some(token::Token::Operation(op2)) = stack.pop() {
-                    if op2.weight() >= op1.weight() {
-                        output.push(token::Token::Operation(op2))
-                    } else {
-                        stack.push(token::Token::Operation(op2))
+            token::Token::Operation(token::Op::Assign) => {
+                // Присваивание выполняет `MathExp` до разбора выражения,
+                // внутри выражения оно недопустимо.
+                return Err(CalcError::RejectedToken(ind));
+            }
+            token::Token::Operation(token::Op::Comma) => {
+                // Разделитель аргументов выталкивает все операции текущего аргумента
+                // вплоть до открывающейся скобки функции.
+                loop {
+                    match stack.pop() {
+                        Some(paren @ (_, token::Token::Operation(token::Op::ParenLeft))) => {
+                            stack.push(paren);
+                            break;
+                        }
+                        Some((last_ind, last_token)) => { output.push(RpnToken::new(last_ind, last_token)) }
+                        None => { return Err(CalcError::UnbalancedParen(ind)); }
                     }
                 }
+                if let Some(count) = commas.last_mut() { *count += 1; }
+            }
+            token::Token::Operation(op1) if op1.is_unary() => {
+                // У префиксной операции нет левого операнда, поэтому ей нечего выталкивать.
+                stack.push((ind, token.clone()))
+            }
+            token::Token::Operation(op1) => {
+                // Выталкиваем в очередь вывода все операции с большим весом,
+                // а также с равным весом, если новая операция левоассоциативна.
+                // Открывающаяся скобка имеет нулевой вес и останавливает выталкивание.
+                while let Some((_, token::Token::Operation(op2))) = stack.last() {
+                    let pop_op2 = op2.weight() > op1.weight()
+                        || (op2.weight() == op1.weight() && op1.assoc() == token::Assoc::Left);
+                    if !pop_op2 { break; }
+                    let (op2_ind, op2) = stack.pop().unwrap();
+                    output.push(RpnToken::new(op2_ind, op2));
+                }
 
-                stack.push(token.clone())
+                stack.push((ind, token.clone()))
             }
         }
     }
     while let Some(last_token_in_stack) = stack.pop() {
         match last_token_in_stack {
-            token::Token::Operation(token::Op::ParenLeft) => {
-                return Err("В выражении отсутствует скобка.".to_string());
+            (ind, token::Token::Operation(token::Op::ParenLeft)) => {
+                return Err(CalcError::UnbalancedParen(ind));
             }
-            _ => { output.push(last_token_in_stack) }
+            (ind, t) => { output.push(RpnToken::new(ind, t)) }
         }
     }
     Ok(output)
-}
\ No newline at end of file
+}
+
+/// Токен в обратной польской записи.
+pub(crate) struct RpnToken {
+    /// Индекс токена во входном векторе.
+    pub index: usize,
+    pub token: token::Token,
+    /// Количество аргументов, если токен - функция.
+    pub argc: usize,
+}
+
+impl RpnToken {
+    fn new(index: usize, token: token::Token) -> Self {
+        Self { index, token, argc: 0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::{Const, Func, Op, Token};
+
+    /// Дерево и вычисление по токенам должны указывать на один и тот же токен
+    /// даже для последовательностей, которые нельзя набрать с клавиатуры.
+    #[test]
+    fn malformed_tokens_report_same_index() {
+        let cases = [
+            vec![],
+            vec![Token::Operand(1.0), Token::Operand(2.0)],
+            vec![Token::Operand(1.0), Token::Operation(Op::Add), Token::Operand(2.0), Token::Constant(Const::Pi)],
+            vec![Token::Operation(Op::ParenLeft), Token::Operand(1.0), Token::Operation(Op::ParenRight), Token::Operand(2.0)],
+            vec![Token::Operation(Op::Add)],
+            vec![Token::Operand(1.0), Token::Operation(Op::Multi)],
+            vec![Token::Function(Func::Sin), Token::Operation(Op::ParenLeft), Token::Operation(Op::ParenRight)],
+        ];
+        let m = MathExp::new();
+        for tokens in cases {
+            let from_tokens = m.eval_tokens(&tokens, 0);
+            let from_tree = expr::Expr::try_from(tokens.as_slice()).and_then(|e| e.eval());
+            assert!(from_tokens.is_err(), "{:?}", tokens);
+            assert_eq!(from_tree, from_tokens, "{:?}", tokens);
+        }
+    }
+}
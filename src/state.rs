@@ -0,0 +1,204 @@
+use serde::{de, Deserialize, Serialize};
+
+use crate::env::Env;
+use crate::format::NumberFormat;
+use crate::history::History;
+use crate::i18n::Language;
+use crate::layout::KeyboardLayout;
+use crate::math_exp;
+use crate::theme::Theme;
+use crate::token;
+
+
+/// Ключ, под которым состояние калькулятора хранится в хранилище eframe.
+pub const STORAGE_KEY: &str = "calculator_state";
+
+/// Версия формата сохраненного состояния.
+///
+/// Увеличивается при несовместимых изменениях формата. Новые поля
+/// добавляются со значениями по умолчанию и версию не меняют.
+pub const STATE_VERSION: u32 = 1;
+
+/// Состояние калькулятора, которое сохраняется между сеансами.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CalcState {
+    pub version: u32,
+    /// Токены текущего выражения.
+    pub tokens: Vec<token::Token>,
+    /// Значение, которое вводилось и еще не стало токеном.
+    pub buffer: String,
+    pub history: History,
+    /// Регистры памяти.
+    pub memory: Vec<f64>,
+    /// Переменные и предыдущий результат.
+    pub env: Env,
+    /// Единицы измерения углов.
+    pub angle: token::AngleMode,
+    /// Язык интерфейса. Если не сохранен, то берется язык системы.
+    pub language: Option<Language>,
+    /// Формат чисел. Если не сохранен, то используется формат, принятый для языка.
+    pub format: Option<NumberFormat>,
+    /// Имя выбранной раскладки клавиатуры.
+    pub layout: String,
+    /// Раскладки, загруженные пользователем.
+    pub custom_layouts: Vec<KeyboardLayout>,
+    /// Тема оформления.
+    pub theme: Theme,
+}
+
+impl Default for CalcState {
+    fn default() -> Self {
+        Self {
+            version: STATE_VERSION,
+            tokens: Vec::new(),
+            buffer: String::new(),
+            history: History::new(),
+            memory: Vec::new(),
+            env: Env::new(),
+            angle: token::AngleMode::default(),
+            language: None,
+            format: None,
+            layout: String::new(),
+            custom_layouts: Vec::new(),
+            theme: Theme::default(),
+        }
+    }
+}
+
+/// Чтение одного поля `name` сохраненного состояния. Остальные поля пропускаются,
+/// поэтому ошибки в них не мешают прочитать это поле.
+struct Field<'a, T> {
+    name: &'static str,
+    value: &'a mut Option<T>,
+}
+
+impl<'de, T: Deserialize<'de>> de::Visitor<'de> for Field<'_, T> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "структура состояния калькулятора")
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some(key) = map.next_key::<Key>()? {
+            if key.0 == self.name {
+                *self.value = map.next_value().ok();
+                return Ok(());
+            }
+            map.next_value::<de::IgnoredAny>()?;
+        }
+        Ok(())
+    }
+}
+
+/// Имя поля структуры.
+struct Key(String);
+
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct KeyVisitor;
+
+        impl de::Visitor<'_> for KeyVisitor {
+            type Value = Key;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "имя поля")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Key, E> {
+                Ok(Key(v.to_string()))
+            }
+        }
+
+        deserializer.deserialize_identifier(KeyVisitor)
+    }
+}
+
+/// Прочитать поле `name` из сохраненного состояния `s`.
+/// Возвращает `None`, если поля нет или его значение не читается.
+fn field<T: de::DeserializeOwned>(s: &str, name: &'static str) -> Option<T> {
+    let mut value = None;
+    let mut deserializer = ron::Deserializer::from_str(s).ok()?;
+    // Ошибка после прочитанного поля (например, обрезанные данные) его не отменяет.
+    let _ = de::Deserializer::deserialize_struct(&mut deserializer, "CalcState", &[], Field { name, value: &mut value });
+    value
+}
+
+impl CalcState {
+    /// Снять состояние с выражения. Настройки интерфейса (раскладку и тему) заполняет приложение.
+    pub fn capture(math_exp: &math_exp::MathExp) -> Self {
+        Self {
+            version: STATE_VERSION,
+            tokens: math_exp.tokens().to_vec(),
+            buffer: math_exp.buffer().to_string(),
+            history: math_exp.history().clone(),
+            memory: (0..math_exp::MEMORY_REGISTERS).map(|reg| math_exp.memory(reg)).collect(),
+            env: math_exp.env().clone(),
+            angle: math_exp.angle_mode(),
+            language: Some(math_exp.language()),
+            format: Some(math_exp.number_format()),
+            layout: String::new(),
+            custom_layouts: Vec::new(),
+            theme: Theme::default(),
+        }
+    }
+
+    /// Восстановить выражение из состояния.
+    ///
+    /// Выражение проверяется по правилам ввода. Если оно повреждено, то калькулятор
+    /// начинает с пустого выражения, а остальное состояние восстанавливается.
+    pub fn restore(self) -> math_exp::MathExp {
+        let mut math_exp = math_exp::MathExp::new();
+        if math_exp.load_tokens(self.tokens) {
+            // Буфер вводим посимвольно, чтобы поврежденное значение не попало в выражение.
+            for c in self.buffer.chars() {
+                math_exp.add(c.to_string().as_str());
+            }
+        }
+        *math_exp.history_mut() = self.history;
+        for (reg, val) in self.memory.into_iter().enumerate() {
+            if val.is_finite() { math_exp.set_memory(reg, val); }
+        }
+        *math_exp.env_mut() = self.env;
+        math_exp.set_angle_mode(self.angle);
+        let lang = self.language.or_else(Language::detect).unwrap_or_default();
+        math_exp.set_language(lang);
+        math_exp.set_number_format(self.format.unwrap_or_else(|| NumberFormat::for_language(lang)));
+        math_exp
+    }
+
+    /// Прочитать состояние из хранилища.
+    ///
+    /// Если данные повреждены или записаны другой версией, то каждое поле читается
+    /// отдельно, а поля, которые прочитать не удалось, получают значения по умолчанию.
+    /// Так ошибка в одном поле не стирает историю, память и настройки.
+    /// `None` возвращается, только если данных нет или в них нет даже версии.
+    pub fn load(storage: &dyn eframe::Storage) -> Option<Self> {
+        let s = storage.get_string(STORAGE_KEY)?;
+        let version: u32 = field(&s, "version")?;
+        if version == STATE_VERSION {
+            if let Ok(state) = ron::from_str::<Self>(&s) { return Some(state); }
+        }
+        let default = Self::default();
+        Some(Self {
+            version: STATE_VERSION,
+            tokens: field(&s, "tokens").unwrap_or(default.tokens),
+            buffer: field(&s, "buffer").unwrap_or(default.buffer),
+            history: field(&s, "history").unwrap_or(default.history),
+            memory: field(&s, "memory").unwrap_or(default.memory),
+            env: field(&s, "env").unwrap_or(default.env),
+            angle: field(&s, "angle").unwrap_or(default.angle),
+            language: field(&s, "language").unwrap_or(default.language),
+            format: field(&s, "format").unwrap_or(default.format),
+            layout: field(&s, "layout").unwrap_or(default.layout),
+            custom_layouts: field(&s, "custom_layouts").unwrap_or(default.custom_layouts),
+            theme: field(&s, "theme").unwrap_or(default.theme),
+        })
+    }
+
+    /// Записать состояние в хранилище.
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, STORAGE_KEY, self);
+    }
+}
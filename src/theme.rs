@@ -0,0 +1,215 @@
+use eframe::egui;
+use eframe::egui::Color32;
+use serde::{Deserialize, Serialize};
+
+use crate::i18n;
+use crate::layout::KeyAction;
+use crate::token;
+
+
+/// Группа клавиш, которая окрашивается одним цветом.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyCategory {
+    /// Цифры и десятичная точка.
+    Digit,
+    /// Операции и скобки.
+    Operator,
+    /// Функции, константы и переменные.
+    Function,
+    /// Действия: очистка, вычисление, память, переключение режимов.
+    Action,
+}
+
+impl KeyCategory {
+    /// Группа клавиши с действием `action`.
+    pub fn of(action: &KeyAction) -> Self {
+        match action {
+            KeyAction::Insert(s) if s == "." || s.parse::<u8>().is_ok() => { KeyCategory::Digit }
+            KeyAction::Insert(s) => {
+                match token::Token::try_from(s.as_str()) {
+                    Ok(token::Token::Operation(_)) => { KeyCategory::Operator }
+                    _ => { KeyCategory::Function }
+                }
+            }
+            _ => { KeyCategory::Action }
+        }
+    }
+}
+
+/// Цвета клавиш одной группы.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeyColors {
+    pub fill: Color32,
+    pub text: Color32,
+}
+
+/// Тема оформления калькулятора.
+///
+/// Применяется к [`egui::Visuals`], а цвета клавиш и экрана используются
+/// клавиатурой и панелями напрямую.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    /// Имя темы: `light`, `dark`, `high_contrast` или `custom` для измененной пользователем.
+    pub name: String,
+    /// Темная основа для виджетов egui.
+    pub dark: bool,
+    /// Контрастные границы у виджетов.
+    pub borders: bool,
+    /// Фон окна и панелей.
+    pub background: Color32,
+    /// Фон экрана калькулятора.
+    pub display: Color32,
+    /// Цвет выражения.
+    pub expression: Color32,
+    /// Цвет результата.
+    pub result: Color32,
+    /// Цвет токена или текста, вызвавшего ошибку.
+    pub error: Color32,
+    pub digit_keys: KeyColors,
+    pub operator_keys: KeyColors,
+    pub function_keys: KeyColors,
+    pub action_keys: KeyColors,
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        let visuals = egui::Visuals::dark();
+        Self {
+            name: "dark".to_string(),
+            dark: true,
+            borders: false,
+            background: visuals.panel_fill,
+            display: visuals.extreme_bg_color,
+            expression: visuals.text_color(),
+            result: Color32::LIGHT_GREEN,
+            error: Color32::RED,
+            digit_keys: KeyColors { fill: Color32::from_gray(70), text: Color32::WHITE },
+            operator_keys: KeyColors { fill: Color32::from_rgb(90, 70, 30), text: Color32::WHITE },
+            function_keys: KeyColors { fill: Color32::from_gray(45), text: Color32::from_gray(220) },
+            action_keys: KeyColors { fill: Color32::from_rgb(40, 60, 90), text: Color32::WHITE },
+        }
+    }
+
+    pub fn light() -> Self {
+        let visuals = egui::Visuals::light();
+        Self {
+            name: "light".to_string(),
+            dark: false,
+            borders: false,
+            background: visuals.panel_fill,
+            display: visuals.extreme_bg_color,
+            expression: visuals.text_color(),
+            result: Color32::DARK_GREEN,
+            error: Color32::from_rgb(200, 0, 0),
+            digit_keys: KeyColors { fill: Color32::WHITE, text: Color32::BLACK },
+            operator_keys: KeyColors { fill: Color32::from_rgb(255, 220, 160), text: Color32::BLACK },
+            function_keys: KeyColors { fill: Color32::from_gray(225), text: Color32::from_gray(40) },
+            action_keys: KeyColors { fill: Color32::from_rgb(190, 215, 245), text: Color32::BLACK },
+        }
+    }
+
+    /// Черный фон и яркие цвета без полутонов.
+    pub fn high_contrast() -> Self {
+        Self {
+            name: "high_contrast".to_string(),
+            dark: true,
+            borders: true,
+            background: Color32::BLACK,
+            display: Color32::BLACK,
+            expression: Color32::WHITE,
+            result: Color32::YELLOW,
+            error: Color32::from_rgb(255, 80, 80),
+            digit_keys: KeyColors { fill: Color32::BLACK, text: Color32::WHITE },
+            operator_keys: KeyColors { fill: Color32::YELLOW, text: Color32::BLACK },
+            function_keys: KeyColors { fill: Color32::from_rgb(0, 0, 120), text: Color32::WHITE },
+            action_keys: KeyColors { fill: Color32::WHITE, text: Color32::BLACK },
+        }
+    }
+
+    /// Встроенные темы.
+    pub fn presets() -> Vec<Self> {
+        vec![Self::dark(), Self::light(), Self::high_contrast()]
+    }
+
+    /// Цвета клавиш группы `category`.
+    pub fn key_colors(&self, category: KeyCategory) -> KeyColors {
+        match category {
+            KeyCategory::Digit => { self.digit_keys }
+            KeyCategory::Operator => { self.operator_keys }
+            KeyCategory::Function => { self.function_keys }
+            KeyCategory::Action => { self.action_keys }
+        }
+    }
+
+    /// Настроить оформление egui под тему.
+    pub fn apply(&self, ctx: &egui::Context) {
+        let mut visuals = if self.dark { egui::Visuals::dark() } else { egui::Visuals::light() };
+        visuals.panel_fill = self.background;
+        visuals.window_fill = self.background;
+        visuals.extreme_bg_color = self.display;
+        visuals.error_fg_color = self.error;
+        if self.borders {
+            let stroke = self.expression;
+            visuals.widgets.noninteractive.fg_stroke.color = stroke;
+            visuals.widgets.inactive.bg_stroke = egui::Stroke::new(1.0, stroke);
+            visuals.widgets.hovered.bg_stroke = egui::Stroke::new(2.0, self.result);
+        }
+        ctx.set_visuals(visuals);
+    }
+
+    /// Редактор цветов темы. Любое изменение превращает тему в пользовательскую.
+    pub fn editor(&mut self, ui: &mut egui::Ui, lang: i18n::Language) {
+        let before = self.clone();
+        egui::Grid::new("theme_editor").num_columns(2).show(ui, |ui| {
+            ui.label(i18n::tr(lang, i18n::Msg::DarkBase));
+            ui.checkbox(&mut self.dark, "");
+            ui.end_row();
+            ui.label(i18n::tr(lang, i18n::Msg::Borders));
+            ui.checkbox(&mut self.borders, "");
+            ui.end_row();
+            for (title, color) in [
+                (i18n::Msg::Background, &mut self.background),
+                (i18n::Msg::Display, &mut self.display),
+                (i18n::Msg::Expression, &mut self.expression),
+                (i18n::Msg::Result, &mut self.result),
+                (i18n::Msg::Error, &mut self.error),
+            ] {
+                ui.label(i18n::tr(lang, title));
+                ui.color_edit_button_srgba(color);
+                ui.end_row();
+            }
+            for (title, keys) in [
+                (i18n::Msg::DigitKeys, &mut self.digit_keys),
+                (i18n::Msg::OperatorKeys, &mut self.operator_keys),
+                (i18n::Msg::FunctionKeys, &mut self.function_keys),
+                (i18n::Msg::ActionKeys, &mut self.action_keys),
+            ] {
+                ui.label(i18n::tr(lang, title));
+                ui.horizontal(|ui| {
+                    ui.color_edit_button_srgba(&mut keys.fill);
+                    ui.color_edit_button_srgba(&mut keys.text);
+                });
+                ui.end_row();
+            }
+        });
+        if *self != before { self.name = "custom".to_string(); }
+    }
+
+    /// Название темы для интерфейса.
+    pub fn title(&self, lang: i18n::Language) -> &'static str {
+        let msg = match self.name.as_str() {
+            "dark" => { i18n::Msg::ThemeDark }
+            "light" => { i18n::Msg::ThemeLight }
+            "high_contrast" => { i18n::Msg::ThemeHighContrast }
+            _ => { i18n::Msg::ThemeCustom }
+        };
+        i18n::tr(lang, msg)
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
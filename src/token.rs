@@ -1,35 +1,363 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::CalcError;
+use crate::format::NumberFormat;
+use crate::i18n::Language;
+
+/// Ассоциативность операции.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Assoc {
+    /// Операции с одинаковым весом вычисляются слева направо: `a-b-c = (a-b)-c`.
+    Left,
+    /// Операции с одинаковым весом вычисляются справа налево: `a^b^c = a^(b^c)`.
+    Right,
+}
+
 pub trait Weight {
     /// Вес операции. Определяет приоритет операций между друг другом.
     /// Операции с наибольшим весом имеют наибольший приоритет.
     fn weight(&self) -> u8;
+    /// Ассоциативность операции. Определяет порядок вычисления операций с одинаковым весом.
+    fn assoc(&self) -> Assoc { Assoc::Left }
+}
+
+/// Единицы измерения углов для тригонометрических функций.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AngleMode {
+    /// Градусы, полный оборот - 360.
+    Deg,
+    /// Радианы, полный оборот - 2π.
+    #[default]
+    Rad,
+    /// Грады, полный оборот - 400.
+    Grad,
+}
+
+impl AngleMode {
+    /// Следующий режим по кругу: DEG -> RAD -> GRAD -> DEG.
+    pub fn next(&self) -> Self {
+        match self {
+            AngleMode::Deg => { AngleMode::Rad }
+            AngleMode::Rad => { AngleMode::Grad }
+            AngleMode::Grad => { AngleMode::Deg }
+        }
+    }
+
+    /// Величина полного оборота в текущих единицах.
+    pub fn full_turn(&self) -> f64 {
+        match self {
+            AngleMode::Deg => { 360.0 }
+            AngleMode::Rad => { std::f64::consts::TAU }
+            AngleMode::Grad => { 400.0 }
+        }
+    }
+
+    /// Синус и косинус угла `x`, заданного в текущих единицах.
+    ///
+    /// Для градусов и градов углы, кратные четверти оборота, вычисляются точно,
+    /// чтобы, например, `sin(180)` в градусах давал ровно 0.
+    pub fn sin_cos(&self, x: f64) -> (f64, f64) {
+        if *self == AngleMode::Rad { return x.sin_cos(); }
+        let turn = self.full_turn();
+        let reduced = x.rem_euclid(turn);
+        // Для малых отрицательных углов `rem_euclid` округляется до полного оборота.
+        let reduced = if reduced >= turn { 0.0 } else { reduced };
+        let quarter = turn / 4.0;
+        if reduced % quarter == 0.0 {
+            match (reduced / quarter) as u8 {
+                0 => { (0.0, 1.0) }
+                1 => { (1.0, 0.0) }
+                2 => { (0.0, -1.0) }
+                _ => { (-1.0, 0.0) }
+            }
+        } else { (reduced / turn * std::f64::consts::TAU).sin_cos() }
+    }
+
+    /// Перевести угол из радиан в текущие единицы.
+    pub fn from_radians(&self, rad: f64) -> f64 {
+        match self {
+            AngleMode::Rad => { rad }
+            _ => { rad / std::f64::consts::TAU * self.full_turn() }
+        }
+    }
+}
+
+impl std::fmt::Display for AngleMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                AngleMode::Deg => { "DEG" }
+                AngleMode::Rad => { "RAD" }
+                AngleMode::Grad => { "GRAD" }
+            }
+        )
+    }
+}
+
+/// Допустимое количество аргументов функции.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Arity {
+    /// Ровно указанное количество аргументов.
+    Fixed(usize),
+    /// От `min` до `max` аргументов включительно.
+    Between(usize, usize),
+    /// Не меньше указанного количества аргументов.
+    AtLeast(usize),
+}
+
+impl Arity {
+    /// Допустимо ли передать функции `count` аргументов.
+    pub fn accepts(&self, count: usize) -> bool {
+        match *self {
+            Arity::Fixed(n) => { count == n }
+            Arity::Between(min, max) => { (min..=max).contains(&count) }
+            Arity::AtLeast(min) => { count >= min }
+        }
+    }
 }
 
-#[derive(Clone)]
+/// Математические функции.
+///
+/// Имена выводятся в русской нотации (tg, ctg, sh), но разбираются
+/// также и в международной (tan, cot, sinh, asin).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Func {
     Sin,
     Cos,
     Tg,
     Ctg,
     Sqrt,
+    // Обратные тригонометрические функции.
+    Arcsin,
+    Arccos,
+    Arctg,
+    Arcctg,
+    // Гиперболические функции.
+    Sh,
+    Ch,
+    Th,
+    Cth,
+    // Обратные гиперболические функции.
+    Arsh,
+    Arch,
+    Arth,
+    Arcth,
+    // Логарифмы и экспонента.
+    /// Натуральный логарифм.
+    Ln,
+    /// Десятичный логарифм.
+    Lg,
+    /// Двоичный логарифм.
+    Log2,
+    /// Экспонента `e^x`.
+    Exp,
+    /// Логарифм по произвольному основанию: `log(основание, x)`.
+    Log,
+    /// Корень произвольной степени: `root(степень, x)`.
+    Root,
+    // Функции нескольких аргументов.
+    /// Наименьший из аргументов.
+    Min,
+    /// Наибольший из аргументов.
+    Max,
+    /// Длина гипотенузы: `hypot(x, y)`.
+    Hypot,
+    /// Угол точки `(x, y)`: `atan2(y, x)`.
+    Atan2,
+    /// Наибольший общий делитель целых чисел.
+    Gcd,
+    /// Наименьшее общее кратное целых чисел.
+    Lcm,
+    /// Округление: `round(x)` до целого или `round(x, знаков)` до указанного количества знаков.
+    Round,
 }
 
 impl Weight for Func {
-    fn weight(&self) -> u8 { 4 }
+    fn weight(&self) -> u8 { 5 }
 }
 
 impl std::fmt::Display for Func {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                Func::Sin => { "sin" }
-                Func::Cos => { "cos" }
-                Func::Tg => { "tg" }
-                Func::Ctg => { "ctg" }
-                Func::Sqrt => { "√" }
+        write!(f, "{}", self.name(Language::default()))
+    }
+}
+
+impl Func {
+    /// Имя функции в нотации, принятой для языка `lang`:
+    /// `tg`, `sh` в русской и `tan`, `sinh` в международной.
+    pub fn name(&self, lang: Language) -> &'static str {
+        let ru = lang == Language::Ru;
+        match self {
+            Func::Sin => { "sin" }
+            Func::Cos => { "cos" }
+            Func::Tg => { if ru { "tg" } else { "tan" } }
+            Func::Ctg => { if ru { "ctg" } else { "cot" } }
+            Func::Sqrt => { "√" }
+            Func::Arcsin => { if ru { "arcsin" } else { "asin" } }
+            Func::Arccos => { if ru { "arccos" } else { "acos" } }
+            Func::Arctg => { if ru { "arctg" } else { "atan" } }
+            Func::Arcctg => { if ru { "arcctg" } else { "acot" } }
+            Func::Sh => { if ru { "sh" } else { "sinh" } }
+            Func::Ch => { if ru { "ch" } else { "cosh" } }
+            Func::Th => { if ru { "th" } else { "tanh" } }
+            Func::Cth => { if ru { "cth" } else { "coth" } }
+            Func::Arsh => { if ru { "arsh" } else { "asinh" } }
+            Func::Arch => { if ru { "arch" } else { "acosh" } }
+            Func::Arth => { if ru { "arth" } else { "atanh" } }
+            Func::Arcth => { if ru { "arcth" } else { "acoth" } }
+            Func::Ln => { "ln" }
+            Func::Lg => { if ru { "lg" } else { "log10" } }
+            Func::Log2 => { "log2" }
+            Func::Exp => { "exp" }
+            Func::Log => { "log" }
+            Func::Root => { "root" }
+            Func::Min => { "min" }
+            Func::Max => { "max" }
+            Func::Hypot => { "hypot" }
+            Func::Atan2 => { "atan2" }
+            Func::Gcd => { "gcd" }
+            Func::Lcm => { "lcm" }
+            Func::Round => { "round" }
+        }
+    }
+
+    /// Допустимое количество аргументов функции.
+    pub fn arity(&self) -> Arity {
+        match self {
+            Func::Log | Func::Root | Func::Hypot | Func::Atan2 => { Arity::Fixed(2) }
+            Func::Min | Func::Max | Func::Gcd | Func::Lcm => { Arity::AtLeast(2) }
+            Func::Round => { Arity::Between(1, 2) }
+            _ => { Arity::Fixed(1) }
+        }
+    }
+
+    /// Вычислить значение функции для аргументов `args`.
+    ///
+    /// `index` - индекс токена функции в выражении, он попадет в ошибку.
+    /// `angle` - единицы измерения углов для тригонометрических функций
+    /// (аргумент прямых функций и результат обратных).
+    pub fn apply(&self, index: usize, args: &[f64], angle: AngleMode) -> Result<f64, CalcError> {
+        if !self.arity().accepts(args.len()) { return Err(CalcError::Arity(index)); }
+        let x = args[0];
+        // Проверка области определения: аргумент должен удовлетворять условию.
+        let domain = |ok: bool| if ok { Ok(()) } else { Err(CalcError::Domain(index)) };
+        let val = match self {
+            Func::Sin => { angle.sin_cos(x).0 }
+            Func::Cos => { angle.sin_cos(x).1 }
+            Func::Tg => {
+                let (sin, cos) = angle.sin_cos(x);
+                if cos == 0.0 { return Err(CalcError::Domain(index)); }
+                sin / cos
             }
-        )
+            Func::Ctg => {
+                let (sin, cos) = angle.sin_cos(x);
+                if sin == 0.0 { return Err(CalcError::Domain(index)); }
+                cos / sin
+            }
+            Func::Sqrt => { x.sqrt() }
+            Func::Arcsin => {
+                domain((-1.0..=1.0).contains(&x))?;
+                angle.from_radians(x.asin())
+            }
+            Func::Arccos => {
+                domain((-1.0..=1.0).contains(&x))?;
+                angle.from_radians(x.acos())
+            }
+            Func::Arctg => { angle.from_radians(x.atan()) }
+            // Область значений арккотангенса - (0, π).
+            Func::Arcctg => { angle.from_radians(std::f64::consts::FRAC_PI_2 - x.atan()) }
+            Func::Sh => { x.sinh() }
+            Func::Ch => { x.cosh() }
+            Func::Th => { x.tanh() }
+            Func::Cth => {
+                domain(x != 0.0)?;
+                1.0 / x.tanh()
+            }
+            Func::Arsh => { x.asinh() }
+            Func::Arch => {
+                domain(x >= 1.0)?;
+                x.acosh()
+            }
+            Func::Arth => {
+                domain(x.abs() < 1.0)?;
+                x.atanh()
+            }
+            Func::Arcth => {
+                domain(x.abs() > 1.0)?;
+                (1.0 / x).atanh()
+            }
+            Func::Ln => {
+                domain(x > 0.0)?;
+                x.ln()
+            }
+            Func::Lg => {
+                domain(x > 0.0)?;
+                x.log10()
+            }
+            Func::Log2 => {
+                domain(x > 0.0)?;
+                x.log2()
+            }
+            Func::Exp => { x.exp() }
+            Func::Log => {
+                let (base, x) = (args[0], args[1]);
+                domain(base > 0.0 && base != 1.0 && x > 0.0)?;
+                x.log(base)
+            }
+            Func::Root => {
+                let (n, x) = (args[0], args[1]);
+                domain(n != 0.0)?;
+                if x < 0.0 {
+                    // Из отрицательного числа извлекается только корень нечетной степени.
+                    domain(n.fract() == 0.0 && n % 2.0 != 0.0)?;
+                    -(-x).powf(1.0 / n)
+                } else { x.powf(1.0 / n) }
+            }
+            Func::Min => { args.iter().copied().fold(f64::INFINITY, f64::min) }
+            Func::Max => { args.iter().copied().fold(f64::NEG_INFINITY, f64::max) }
+            Func::Hypot => { args[0].hypot(args[1]) }
+            Func::Atan2 => { angle.from_radians(args[0].atan2(args[1])) }
+            Func::Gcd | Func::Lcm => {
+                domain(args.iter().all(|a| a.fract() == 0.0))?;
+                let gcd = |mut a: f64, mut b: f64| {
+                    while b != 0.0 { (a, b) = (b, a % b); }
+                    a.abs()
+                };
+                let mut acc = args[0].abs();
+                for &a in &args[1..] {
+                    acc = match self {
+                        Func::Gcd => { gcd(acc, a) }
+                        // НОК с нулем равно нулю.
+                        _ => { if acc == 0.0 || a == 0.0 { 0.0 } else { (acc * a).abs() / gcd(acc, a) } }
+                    };
+                }
+                acc
+            }
+            Func::Round => {
+                let digits = args.get(1).copied().unwrap_or(0.0);
+                domain(digits.fract() == 0.0)?;
+                // Больше знаков, чем помещается в f64, число не меняет,
+                // а масштаб 10^digits при этом переполнился бы.
+                if x == 0.0 || digits > f64::DIGITS as f64 - x.abs().log10().floor() {
+                    x
+                } else {
+                    // Округление до разрядов левее самого старшего дает ноль.
+                    let digits = digits.max(-(f64::MAX_10_EXP as f64) - 1.0);
+                    if digits <= f64::MAX_10_EXP as f64 {
+                        let scale = 10f64.powf(digits);
+                        (x * scale).round() / scale
+                    } else {
+                        // Малые числа округляются и до знаков дальше 10^-308:
+                        // масштаб делится на два множителя, чтобы не переполниться.
+                        let scale = 10f64.powf(digits / 2.0);
+                        (x * scale * scale).round() / scale / scale
+                    }
+                }
+            }
+        };
+        CalcError::check(index, val)
     }
 }
 
@@ -40,16 +368,41 @@ impl TryFrom<&str> for Func {
         match s {
             "sin" => Ok(Func::Sin),
             "cos" => Ok(Func::Cos),
-            "tg" => Ok(Func::Tg),
-            "ctg" => Ok(Func::Ctg),
+            "tg" | "tan" => Ok(Func::Tg),
+            "ctg" | "cot" => Ok(Func::Ctg),
             "√" => Ok(Func::Sqrt),
+            "arcsin" | "asin" => Ok(Func::Arcsin),
+            "arccos" | "acos" => Ok(Func::Arccos),
+            "arctg" | "arctan" | "atan" => Ok(Func::Arctg),
+            "arcctg" | "arccot" | "acot" => Ok(Func::Arcctg),
+            "sh" | "sinh" => Ok(Func::Sh),
+            "ch" | "cosh" => Ok(Func::Ch),
+            "th" | "tanh" => Ok(Func::Th),
+            "cth" | "coth" => Ok(Func::Cth),
+            "arsh" | "arsinh" | "asinh" => Ok(Func::Arsh),
+            "arch" | "arcosh" | "acosh" => Ok(Func::Arch),
+            "arth" | "artanh" | "atanh" => Ok(Func::Arth),
+            "arcth" | "arcoth" | "acoth" => Ok(Func::Arcth),
+            "ln" => Ok(Func::Ln),
+            "lg" | "log10" => Ok(Func::Lg),
+            "log2" => Ok(Func::Log2),
+            "exp" => Ok(Func::Exp),
+            "log" => Ok(Func::Log),
+            "root" => Ok(Func::Root),
+            "min" => Ok(Func::Min),
+            "max" => Ok(Func::Max),
+            "hypot" => Ok(Func::Hypot),
+            "atan2" => Ok(Func::Atan2),
+            "gcd" => Ok(Func::Gcd),
+            "lcm" => Ok(Func::Lcm),
+            "round" => Ok(Func::Round),
             _ => Err(())
         }
     }
 }
 
 /// Алгебраические операции.
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Op {
     /// Сложение - соответствует знаку '+'.
     Add,
@@ -61,9 +414,17 @@ pub enum Op {
     Div,
     /// Возведение в степень - соответствует знаку '^'.
     Exp,
+    /// Унарный минус (смена знака) - соответствует знаку '-' перед операндом.
+    Neg,
+    /// Унарный плюс - соответствует знаку '+' перед операндом.
+    Pos,
     /// Символы ограничения области вычислений.
     ParenLeft,
     ParenRight,
+    /// Разделитель аргументов функции - соответствует знаку ',' (или ';' при десятичной запятой).
+    Comma,
+    /// Присваивание значения переменной - соответствует знаку '='.
+    Assign,
 }
 
 impl Weight for Op {
@@ -71,8 +432,17 @@ impl Weight for Op {
         match self {
             Op::Add | Op::Sub => { 1 }
             Op::Multi | Op::Div => { 2 }
-            Op::Exp => { 3 }
-            Op::ParenRight | Op::ParenLeft => { 0 }
+            // Унарный знак связывает сильнее умножения, но слабее возведения в степень: -2^2 = -(2^2).
+            Op::Neg | Op::Pos => { 3 }
+            Op::Exp => { 4 }
+            Op::ParenRight | Op::ParenLeft | Op::Comma | Op::Assign => { 0 }
+        }
+    }
+
+    fn assoc(&self) -> Assoc {
+        match self {
+            Op::Exp | Op::Neg | Op::Pos => { Assoc::Right }
+            _ => { Assoc::Left }
         }
     }
 }
@@ -89,13 +459,59 @@ impl std::fmt::Display for Op {
                 Op::Multi => { "*" }
                 Op::Div => { "/" }
                 Op::Exp => { "^" }
+                Op::Neg => { "-" }
+                Op::Pos => { "+" }
                 Op::ParenLeft => { "(" }
                 Op::ParenRight => { ")" }
+                Op::Comma => { "," }
+                Op::Assign => { "=" }
             }
         )
     }
 }
 
+impl Op {
+    /// Является ли операция унарной (префиксной).
+    pub fn is_unary(&self) -> bool {
+        matches!(self, Op::Neg | Op::Pos)
+    }
+
+    /// Вычислить значение унарной операции над `x`.
+    ///
+    /// `index` - индекс токена операции в выражении, он попадет в ошибку.
+    pub fn apply_unary(&self, index: usize, x: f64) -> Result<f64, CalcError> {
+        match self {
+            Op::Neg => { Ok(-x) }
+            Op::Pos => { Ok(x) }
+            _ => { Err(CalcError::RejectedToken(index)) }
+        }
+    }
+
+    /// Вычислить значение бинарной операции над `a` и `b`.
+    ///
+    /// `index` - индекс токена операции в выражении, он попадет в ошибку.
+    pub fn apply(&self, index: usize, a: f64, b: f64) -> Result<f64, CalcError> {
+        let val = match self {
+            Op::Add => { a + b }
+            Op::Sub => { a - b }
+            Op::Multi => { a * b }
+            Op::Div => {
+                if b == 0.0 { return Err(CalcError::DivisionByZero(index)); }
+                a / b
+            }
+            Op::Exp => {
+                if a == 0.0 && b < 0.0 { return Err(CalcError::DivisionByZero(index)); }
+                a.powf(b)
+            }
+            // Скобки и разделитель аргументов удаляются при разборе выражения,
+            // присваивание выполняет `MathExp`, а унарные операции вычисляются в `apply_unary`.
+            Op::ParenLeft | Op::ParenRight | Op::Comma | Op::Assign | Op::Neg | Op::Pos => {
+                return Err(CalcError::RejectedToken(index));
+            }
+        };
+        CalcError::check(index, val)
+    }
+}
 
 impl TryFrom<&str> for Op {
     type Error = ();
@@ -109,19 +525,110 @@ impl TryFrom<&str> for Op {
             "+" => Ok(Op::Add),
             ")" => Ok(Op::ParenRight),
             "(" => Ok(Op::ParenLeft),
+            "," | ";" => Ok(Op::Comma),
+            "=" => Ok(Op::Assign),
             _ => Err(())
         }
     }
 }
 
-#[derive(Clone)]
+/// Математические константы.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Const {
+    /// Число π - отношение длины окружности к ее диаметру.
+    Pi,
+    /// Число e - основание натурального логарифма.
+    E,
+    /// Золотое сечение φ.
+    Phi,
+    /// Число τ = 2π.
+    Tau,
+}
+
+impl Const {
+    /// Значение константы с полной точностью f64.
+    pub fn value(&self) -> f64 {
+        match self {
+            Const::Pi => { std::f64::consts::PI }
+            Const::E => { std::f64::consts::E }
+            Const::Phi => { 1.618_033_988_749_895 }
+            Const::Tau => { std::f64::consts::TAU }
+        }
+    }
+}
+
+impl std::fmt::Display for Const {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Const::Pi => { "π" }
+                Const::E => { "e" }
+                Const::Phi => { "φ" }
+                Const::Tau => { "τ" }
+            }
+        )
+    }
+}
+
+impl TryFrom<&str> for Const {
+    type Error = ();
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "π" | "pi" => Ok(Const::Pi),
+            "e" => Ok(Const::E),
+            "φ" | "phi" => Ok(Const::Phi),
+            "τ" | "tau" => Ok(Const::Tau),
+            _ => Err(())
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Token {
-    /// Одинарные функции.
+    /// Функции.
     Function(Func),
     /// Операции над числами.
     Operation(Op),
     /// Число (вещественное).
     Operand(f64),
+    /// Математическая константа. Заменяется значением только при вычислении.
+    Constant(Const),
+    /// Именованная переменная. Значение берется из окружения при вычислении.
+    Variable(String),
+    /// Результат предыдущего вычисления.
+    Ans,
+}
+
+impl Token {
+    /// Является ли токен значением (числом, константой, переменной или предыдущим результатом).
+    pub fn is_operand(&self) -> bool {
+        matches!(self, Token::Operand(_) | Token::Constant(_) | Token::Variable(_) | Token::Ans)
+    }
+
+    /// Токен в виде строки с именами функций в нотации языка `lang`.
+    pub fn localized(&self, lang: Language) -> String {
+        match self {
+            Token::Function(func) => { func.name(lang).to_string() }
+            _ => { self.to_string() }
+        }
+    }
+
+    /// Токен в виде строки в нотации языка `lang` с числами в формате `format`.
+    pub fn formatted(&self, lang: Language, format: &NumberFormat) -> String {
+        match self {
+            Token::Operand(val) => { format.format(*val) }
+            Token::Operation(Op::Comma) => { format.separator().to_string() }
+            _ => { self.localized(lang) }
+        }
+    }
+
+    /// Может ли строка быть именем переменной: начинается с буквы и состоит из букв и цифр.
+    pub fn is_identifier(s: &str) -> bool {
+        s.chars().next().map_or(false, char::is_alphabetic) && s.chars().all(char::is_alphanumeric)
+    }
 }
 
 impl TryFrom<&str> for Token {
@@ -132,10 +639,17 @@ impl TryFrom<&str> for Token {
             Ok(Token::Operation(o))
         } else if let Ok(f) = Func::try_from(s) {
             Ok(Token::Function(f))
+        } else if let Ok(c) = Const::try_from(s) {
+            Ok(Token::Constant(c))
+        } else if s == "Ans" || s == "ans" {
+            Ok(Token::Ans)
         } else if let Ok(val) = s.parse::<f64>() {
-            if val.is_infinite() {
+            // Строки вида "inf" и "NaN" также разбираются как f64, но числами не являются.
+            if !val.is_finite() {
                 Err(())
             } else { Ok(Token::Operand(val)) }
+        } else if Token::is_identifier(s) {
+            Ok(Token::Variable(s.to_string()))
         } else { Err(()) }
     }
 }
@@ -149,6 +663,9 @@ impl std::fmt::Display for Token {
                 Token::Function(func) => { func.to_string() }
                 Token::Operation(op) => { op.to_string() }
                 Token::Operand(o) => { o.to_string() }
+                Token::Constant(c) => { c.to_string() }
+                Token::Variable(name) => { name.clone() }
+                Token::Ans => { "Ans".to_string() }
             }
         )
     }
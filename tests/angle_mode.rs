@@ -0,0 +1,41 @@
+use calculator_wasm_rust_pwa::token::AngleMode;
+
+#[test]
+fn exact_degrees() {
+    assert_eq!(AngleMode::Deg.sin_cos(0.0), (0.0, 1.0));
+    assert_eq!(AngleMode::Deg.sin_cos(90.0), (1.0, 0.0));
+    assert_eq!(AngleMode::Deg.sin_cos(180.0), (0.0, -1.0));
+    assert_eq!(AngleMode::Deg.sin_cos(270.0), (-1.0, 0.0));
+    assert_eq!(AngleMode::Deg.sin_cos(360.0), (0.0, 1.0));
+    assert_eq!(AngleMode::Deg.sin_cos(-90.0), (-1.0, 0.0));
+}
+
+#[test]
+fn exact_grads() {
+    assert_eq!(AngleMode::Grad.sin_cos(0.0), (0.0, 1.0));
+    assert_eq!(AngleMode::Grad.sin_cos(100.0), (1.0, 0.0));
+    assert_eq!(AngleMode::Grad.sin_cos(200.0), (0.0, -1.0));
+    assert_eq!(AngleMode::Grad.sin_cos(300.0), (-1.0, 0.0));
+    assert_eq!(AngleMode::Grad.sin_cos(400.0), (0.0, 1.0));
+    assert_eq!(AngleMode::Grad.sin_cos(-100.0), (-1.0, 0.0));
+}
+
+#[test]
+fn tiny_negative_angles() {
+    for mode in [AngleMode::Deg, AngleMode::Grad] {
+        for x in [-1e-20, -1e-300, -f64::MIN_POSITIVE] {
+            let (sin, cos) = mode.sin_cos(x);
+            assert!(sin.abs() < 1e-15, "{:?} sin({}) = {}", mode, x, sin);
+            assert!((cos - 1.0).abs() < 1e-15, "{:?} cos({}) = {}", mode, x, cos);
+        }
+    }
+}
+
+#[test]
+fn non_quarter_angles() {
+    let (sin, cos) = AngleMode::Deg.sin_cos(30.0);
+    assert!((sin - 0.5).abs() < 1e-12);
+    assert!((cos - 3f64.sqrt() / 2.0).abs() < 1e-12);
+    let (sin, _) = AngleMode::Deg.sin_cos(-30.0);
+    assert!((sin + 0.5).abs() < 1e-12);
+}
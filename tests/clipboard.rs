@@ -0,0 +1,60 @@
+use calculator_wasm_rust_pwa::keyboard::CalcKeyboard;
+use calculator_wasm_rust_pwa::math_exp::MathExp;
+use eframe::egui;
+
+/// Передать клавиатуре калькулятора событие и вернуть скопированный текст.
+fn run(m: &mut MathExp, event: egui::Event) -> String {
+    let ctx = egui::Context::default();
+    let input = egui::RawInput { events: vec![event], ..Default::default() };
+    let output = ctx.run(input, |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| CalcKeyboard::from_buffer(m).show(ui));
+    });
+    output.platform_output.copied_text
+}
+
+#[test]
+fn copy_keeps_expression() {
+    let mut m = MathExp::parse("12*3").unwrap();
+    assert_eq!(run(&mut m, egui::Event::Copy), "12*3");
+    assert_eq!(m.to_string(), "12*3");
+}
+
+#[test]
+fn cut_clears_expression() {
+    let mut m = MathExp::parse("12*3").unwrap();
+    assert_eq!(run(&mut m, egui::Event::Cut), "12*3");
+    assert_eq!(m.to_string(), "");
+}
+
+#[test]
+fn paste_appends() {
+    let mut m = MathExp::parse("2+").unwrap();
+    run(&mut m, egui::Event::Paste("3*4".to_string()));
+    assert_eq!(m.evaluate(), Ok(14.0));
+}
+
+#[test]
+fn paste_exponent_notation() {
+    let mut m = MathExp::new();
+    run(&mut m, egui::Event::Paste("6.02e23/2".to_string()));
+    assert_eq!(m.evaluate(), Ok(3.01e23));
+    assert!(m.get_rejected().is_none());
+}
+
+#[test]
+fn copied_result_pastes_back() {
+    let mut m = MathExp::parse("10^20*3").unwrap();
+    m.calculate();
+    let copied = run(&mut m, egui::Event::Copy);
+    assert_eq!(copied, "300000000000000000000");
+    let mut other = MathExp::new();
+    run(&mut other, egui::Event::Paste(copied));
+    assert_eq!(other.evaluate(), Ok(3e20));
+    run(&mut other, egui::Event::Paste("+1.5E-3".to_string()));
+    assert_eq!(other.evaluate(), Ok(3e20 + 1.5e-3));
+    // Результат на экране записан в экспоненциальной форме и тоже вставляется.
+    assert_eq!(m.get_output(), "3e20");
+    let mut other = MathExp::new();
+    run(&mut other, egui::Event::Paste(m.get_output()));
+    assert_eq!(other.evaluate(), Ok(3e20));
+}
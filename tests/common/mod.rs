@@ -0,0 +1,56 @@
+//! Общие вспомогательные функции интеграционных тестов.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use calculator_wasm_rust_pwa::error::CalcError;
+use calculator_wasm_rust_pwa::expr::Expr;
+use calculator_wasm_rust_pwa::math_exp::MathExp;
+
+/// Выражение, разобранное из строки.
+pub fn parse(s: &str) -> MathExp {
+    MathExp::parse(s).unwrap()
+}
+
+/// Результат вычисления выражения.
+pub fn try_eval(s: &str) -> Result<f64, CalcError> {
+    parse(s).evaluate()
+}
+
+/// Значение выражения, которое должно вычисляться без ошибок.
+pub fn eval(s: &str) -> f64 {
+    try_eval(s).unwrap()
+}
+
+/// Дерево выражения.
+pub fn expr(s: &str) -> Expr {
+    parse(s).to_expr().unwrap()
+}
+
+/// Запись выражения, восстановленная из дерева.
+pub fn display(s: &str) -> String {
+    expr(s).to_string()
+}
+
+/// Дописать строку к выражению, вычислить его и вернуть вывод.
+pub fn run(m: &mut MathExp, s: &str) -> String {
+    m.push_str(s).unwrap();
+    m.calculate();
+    m.get_output()
+}
+
+/// Хранилище eframe в памяти.
+#[derive(Default)]
+pub struct MemoryStorage(pub HashMap<String, String>);
+
+impl eframe::Storage for MemoryStorage {
+    fn get_string(&self, key: &str) -> Option<String> {
+        self.0.get(key).cloned()
+    }
+
+    fn set_string(&mut self, key: &str, value: String) {
+        self.0.insert(key.to_string(), value);
+    }
+
+    fn flush(&mut self) {}
+}
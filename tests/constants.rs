@@ -0,0 +1,26 @@
+use calculator_wasm_rust_pwa::token::{Const, Token};
+
+mod common;
+
+use common::{display, eval, parse};
+
+#[test]
+fn constants_stay_symbolic() {
+    let m = parse("2*π");
+    assert_eq!(m.tokens()[2], Token::Constant(Const::Pi));
+    assert_eq!(m.to_string(), "2*π");
+    assert_eq!(m.copy_text(), "2*π");
+    assert_eq!(display("2*π"), "2*π");
+    // Имя латиницей заменяется символом константы.
+    assert_eq!(parse("pi+tau").to_string(), "π+τ");
+    assert_eq!(display("e^2-φ"), "e^2-φ");
+}
+
+#[test]
+fn constants_have_full_precision() {
+    assert_eq!(eval("π"), std::f64::consts::PI);
+    assert_eq!(eval("e"), std::f64::consts::E);
+    assert_eq!(eval("τ"), std::f64::consts::TAU);
+    assert_eq!(eval("φ"), (1.0 + 5f64.sqrt()) / 2.0);
+    assert_eq!(eval("2*π"), 2.0 * std::f64::consts::PI);
+}
@@ -0,0 +1,49 @@
+use calculator_wasm_rust_pwa::error::CalcError;
+use calculator_wasm_rust_pwa::expr::Expr;
+
+mod common;
+
+use common::{expr, parse};
+
+#[test]
+fn division_by_zero_points_at_operator() {
+    // Токены: 1 / 0 + 2, знак деления - токен с индексом 1.
+    assert_eq!(expr("1/0+2").eval(), Err(CalcError::DivisionByZero(1)));
+    // Токены: 2 * ( 3 / 0 ), знак деления - токен с индексом 4.
+    assert_eq!(expr("2*(3/0)").eval(), Err(CalcError::DivisionByZero(4)));
+}
+
+#[test]
+fn errors_match_token_evaluation() {
+    for s in ["1/0+2", "2*(3/0)", "ln(0-1)+1", "5+√(0-4)", "x+1", "2^(1/0)"] {
+        let mut m = parse(s);
+        let from_tokens = m.evaluate();
+        assert_eq!(m.to_expr().unwrap().eval(), from_tokens, "{}", s);
+    }
+}
+
+#[test]
+fn nodes_keep_token_index() {
+    match expr("1+2*3") {
+        Expr::Binary(_, left, right, ind) => {
+            assert_eq!(ind, 1);
+            assert_eq!(left.index(), 0);
+            assert_eq!(right.index(), 3);
+        }
+        e => { panic!("{:?}", e) }
+    }
+}
+
+#[test]
+fn equality_ignores_token_index() {
+    // Подвыражение 1+2 стоит в разных местах, но деревья равны.
+    let inner = expr("1+2");
+    match expr("3*(1+2)") {
+        Expr::Binary(_, _, right, _) => {
+            assert_ne!(right.index(), inner.index());
+            assert_eq!(*right, inner);
+        }
+        e => { panic!("{:?}", e) }
+    }
+    assert_ne!(expr("1+2"), expr("2+1"));
+}
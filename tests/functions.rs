@@ -0,0 +1,191 @@
+use calculator_wasm_rust_pwa::error::CalcError;
+use calculator_wasm_rust_pwa::i18n::Language;
+use calculator_wasm_rust_pwa::math_exp::MathExp;
+use calculator_wasm_rust_pwa::token::{AngleMode, Arity, Func};
+
+mod common;
+
+use common::try_eval;
+
+fn apply(f: Func, args: &[f64]) -> Result<f64, CalcError> {
+    f.apply(7, args, AngleMode::Rad)
+}
+
+
+#[test]
+fn variadic_functions() {
+    assert_eq!(apply(Func::Min, &[3.0, -1.0, 2.0]), Ok(-1.0));
+    assert_eq!(apply(Func::Max, &[3.0, -1.0, 2.0, 8.5]), Ok(8.5));
+    assert_eq!(apply(Func::Gcd, &[12.0, 18.0, 27.0]), Ok(3.0));
+    assert_eq!(apply(Func::Gcd, &[-4.0, 6.0]), Ok(2.0));
+    assert_eq!(apply(Func::Lcm, &[4.0, 6.0, 10.0]), Ok(60.0));
+    assert_eq!(apply(Func::Lcm, &[0.0, 5.0]), Ok(0.0));
+    assert_eq!(try_eval("max(1, 5, 3) + min(4, 2)"), Ok(7.0));
+    assert_eq!(try_eval("gcd(48, 36, 60)"), Ok(12.0));
+}
+
+#[test]
+fn round() {
+    assert_eq!(apply(Func::Round, &[2.5]), Ok(3.0));
+    assert_eq!(apply(Func::Round, &[-2.4]), Ok(-2.0));
+    assert_eq!(apply(Func::Round, &[1.23456, 2.0]), Ok(1.23));
+    assert_eq!(apply(Func::Round, &[1.23456, 3.0]), Ok(1.235));
+    assert_eq!(apply(Func::Round, &[1234.0, -2.0]), Ok(1200.0));
+    assert_eq!(apply(Func::Round, &[1.5, 0.5]), Err(CalcError::Domain(7)));
+}
+
+#[test]
+fn arity_model() {
+    assert_eq!(Func::Min.arity(), Arity::AtLeast(2));
+    assert_eq!(Func::Round.arity(), Arity::Between(1, 2));
+    assert_eq!(Func::Sin.arity(), Arity::Fixed(1));
+    assert!(Arity::AtLeast(2).accepts(5));
+    assert!(!Arity::AtLeast(2).accepts(1));
+    assert!(Arity::Between(1, 2).accepts(1));
+    assert!(!Arity::Between(1, 2).accepts(3));
+    assert!(!Arity::Fixed(1).accepts(0));
+}
+
+#[test]
+fn wrong_argument_count() {
+    // Ноль аргументов.
+    assert_eq!(apply(Func::Max, &[]), Err(CalcError::Arity(7)));
+    assert_eq!(apply(Func::Sin, &[]), Err(CalcError::Arity(7)));
+    // Слишком мало и слишком много аргументов.
+    assert_eq!(apply(Func::Gcd, &[4.0]), Err(CalcError::Arity(7)));
+    assert_eq!(apply(Func::Round, &[1.0, 2.0, 3.0]), Err(CalcError::Arity(7)));
+    assert_eq!(apply(Func::Log, &[8.0]), Err(CalcError::Arity(7)));
+    // Ошибка указывает на токен функции.
+    assert_eq!(try_eval("1+round(1, 2, 3)"), Err(CalcError::Arity(2)));
+    assert_eq!(try_eval("min(5)"), Err(CalcError::Arity(0)));
+}
+
+#[test]
+fn non_integer_gcd() {
+    assert_eq!(apply(Func::Gcd, &[4.5, 6.0]), Err(CalcError::Domain(7)));
+    assert_eq!(apply(Func::Lcm, &[4.0, 0.5]), Err(CalcError::Domain(7)));
+    assert_eq!(try_eval("gcd(2.5, 5)"), Err(CalcError::Domain(0)));
+}
+
+#[test]
+fn comma_only_inside_function_call() {
+    assert!(MathExp::parse("(1,2)+3").is_err());
+    assert!(MathExp::parse("max((1+2),3)").is_ok());
+    assert!(MathExp::parse("max((1,2),3)").is_err());
+    assert!(MathExp::parse("max(1,(2+3)*2)").is_ok());
+
+    let m = MathExp::parse("(1").unwrap();
+    assert!(!m.can_accept(","));
+    let m = MathExp::parse("max(1").unwrap();
+    assert!(m.can_accept(","));
+    let m = MathExp::parse("max(1,(2").unwrap();
+    assert!(!m.can_accept(","));
+}
+
+#[test]
+fn round_to_many_digits() {
+    assert_eq!(apply(Func::Round, &[1.23456, 309.0]), Ok(1.23456));
+    assert_eq!(apply(Func::Round, &[1.23456, 1e6]), Ok(1.23456));
+    // Малые числа округляются и до знаков дальше 10^-308.
+    let tiny = apply(Func::Round, &[1.26e-309, 310.0]).unwrap();
+    assert!((tiny / 1.3e-309 - 1.0).abs() < 1e-6, "{}", tiny);
+    assert_eq!(apply(Func::Round, &[0.0, 400.0]), Ok(0.0));
+    assert_eq!(apply(Func::Round, &[1234.0, -400.0]), Ok(0.0));
+    assert_eq!(apply(Func::Round, &[1e300, 20.0]), Ok(1e300));
+}
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-12
+}
+
+#[test]
+fn inverse_trigonometric() {
+    assert!(close(try_eval("arcsin(1)").unwrap(), std::f64::consts::FRAC_PI_2));
+    assert!(close(try_eval("arccos(-1)").unwrap(), std::f64::consts::PI));
+    assert!(close(try_eval("arctg(1)").unwrap(), std::f64::consts::FRAC_PI_4));
+    assert!(close(try_eval("arcctg(-1)").unwrap(), 3.0 * std::f64::consts::FRAC_PI_4));
+    // Результат обратных функций - в выбранных единицах углов.
+    assert!(close(Func::Arcsin.apply(0, &[0.5], AngleMode::Deg).unwrap(), 30.0));
+    assert!(close(Func::Arctg.apply(0, &[1.0], AngleMode::Grad).unwrap(), 50.0));
+}
+
+#[test]
+fn hyperbolic() {
+    assert_eq!(try_eval("sh(0)"), Ok(0.0));
+    assert_eq!(try_eval("ch(0)"), Ok(1.0));
+    assert!(close(try_eval("th(1)").unwrap(), 1f64.tanh()));
+    assert!(close(try_eval("cth(1)").unwrap(), 1.0 / 1f64.tanh()));
+    assert!(close(try_eval("arsh(sh(2))").unwrap(), 2.0));
+    assert!(close(try_eval("arch(ch(2))").unwrap(), 2.0));
+    assert!(close(try_eval("arth(th(0.5))").unwrap(), 0.5));
+    assert!(close(try_eval("arcth(cth(0.5))").unwrap(), 0.5));
+    // Гиперболические функции не зависят от единиц углов.
+    assert_eq!(Func::Sh.apply(0, &[1.0], AngleMode::Deg), Ok(1f64.sinh()));
+}
+
+#[test]
+fn inverse_functions_domain() {
+    assert_eq!(try_eval("arcsin(2)"), Err(CalcError::Domain(0)));
+    assert_eq!(try_eval("1+arccos(-1.5)"), Err(CalcError::Domain(2)));
+    assert_eq!(try_eval("arth(1)"), Err(CalcError::Domain(0)));
+    assert_eq!(try_eval("arth(-1)"), Err(CalcError::Domain(0)));
+    assert_eq!(try_eval("arcth(0.5)"), Err(CalcError::Domain(0)));
+    assert_eq!(try_eval("arch(0.5)"), Err(CalcError::Domain(0)));
+    assert_eq!(try_eval("cth(0)"), Err(CalcError::Domain(0)));
+}
+
+#[test]
+fn function_aliases() {
+    assert_eq!(Func::try_from("tg"), Ok(Func::Tg));
+    assert_eq!(Func::try_from("tan"), Ok(Func::Tg));
+    assert_eq!(Func::try_from("arctg"), Ok(Func::Arctg));
+    assert_eq!(Func::try_from("arctan"), Ok(Func::Arctg));
+    assert_eq!(Func::try_from("atan"), Ok(Func::Arctg));
+    assert_eq!(Func::try_from("sinh"), Ok(Func::Sh));
+    assert_eq!(Func::try_from("acoth"), Ok(Func::Arcth));
+    assert_eq!(Func::try_from("tangent"), Err(()));
+    assert_eq!(try_eval("tan(1)"), try_eval("tg(1)"));
+    assert_eq!(try_eval("atanh(0.5)"), try_eval("arth(0.5)"));
+    // Имя выводится в нотации языка интерфейса.
+    assert_eq!(Func::Tg.name(Language::Ru), "tg");
+    assert_eq!(Func::Tg.name(Language::En), "tan");
+    assert_eq!(Func::Arctg.name(Language::Ru), "arctg");
+    assert_eq!(Func::Arctg.name(Language::En), "atan");
+    assert_eq!(Func::Arth.name(Language::En), "atanh");
+}
+
+#[test]
+fn logarithms_and_roots() {
+    assert_eq!(try_eval("ln(1)"), Ok(0.0));
+    assert!(close(try_eval("ln(exp(2))").unwrap(), 2.0));
+    assert_eq!(try_eval("lg(1000)"), Ok(3.0));
+    assert_eq!(try_eval("log10(0.01)"), Ok(-2.0));
+    assert_eq!(try_eval("log2(8)"), Ok(3.0));
+    assert_eq!(try_eval("exp(0)"), Ok(1.0));
+    assert!(close(try_eval("log(2, 1024)").unwrap(), 10.0));
+    assert!(close(try_eval("log(10, 0.1)").unwrap(), -1.0));
+    assert!(close(try_eval("root(3, 27)").unwrap(), 3.0));
+    assert!(close(try_eval("root(4, 16)").unwrap(), 2.0));
+    // Корень нечетной степени из отрицательного числа.
+    assert!(close(try_eval("root(3, -8)").unwrap(), -2.0));
+    assert!(close(try_eval("root(-2, 4)").unwrap(), 0.5));
+}
+
+#[test]
+fn logarithms_and_roots_domain() {
+    // Логарифм неположительного числа.
+    assert_eq!(try_eval("ln(0)"), Err(CalcError::Domain(0)));
+    assert_eq!(try_eval("lg(-1)"), Err(CalcError::Domain(0)));
+    assert_eq!(try_eval("2*log2(0)"), Err(CalcError::Domain(2)));
+    assert_eq!(apply(Func::Log, &[2.0, 0.0]), Err(CalcError::Domain(7)));
+    assert_eq!(apply(Func::Log, &[2.0, -4.0]), Err(CalcError::Domain(7)));
+    // Недопустимое основание.
+    assert_eq!(try_eval("log(1, 5)"), Err(CalcError::Domain(0)));
+    assert_eq!(apply(Func::Log, &[0.0, 5.0]), Err(CalcError::Domain(7)));
+    assert_eq!(apply(Func::Log, &[-2.0, 4.0]), Err(CalcError::Domain(7)));
+    // Корень четной или дробной степени из отрицательного числа и корень нулевой степени.
+    assert_eq!(try_eval("root(2, -4)"), Err(CalcError::Domain(0)));
+    assert_eq!(apply(Func::Root, &[4.0, -16.0]), Err(CalcError::Domain(7)));
+    assert_eq!(apply(Func::Root, &[2.5, -1.0]), Err(CalcError::Domain(7)));
+    assert_eq!(apply(Func::Root, &[0.0, 5.0]), Err(CalcError::Domain(7)));
+}
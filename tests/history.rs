@@ -0,0 +1,48 @@
+use calculator_wasm_rust_pwa::format::NumberFormat;
+use calculator_wasm_rust_pwa::history::{History, HistoryEntry};
+use calculator_wasm_rust_pwa::i18n::Language;
+use calculator_wasm_rust_pwa::math_exp::MathExp;
+use calculator_wasm_rust_pwa::token::Token;
+
+fn entry(result: f64) -> HistoryEntry {
+    HistoryEntry { tokens: vec![Token::Operand(result)], result, timestamp: 0.0 }
+}
+
+#[test]
+fn capacity_drops_oldest() {
+    let mut h = History::with_capacity(3);
+    for i in 0..5 {
+        h.push_entry(entry(i as f64));
+    }
+    assert_eq!(h.len(), 3);
+    assert_eq!(h.get(0).map(|e| e.result), Some(2.0));
+    assert_eq!(h.last().map(|e| e.result), Some(4.0));
+    let results: Vec<f64> = h.iter().rev().map(|e| e.result).collect();
+    assert_eq!(results, vec![4.0, 3.0, 2.0]);
+    h.clear();
+    assert!(h.is_empty());
+}
+
+#[test]
+fn calculations_are_recorded() {
+    let mut m = MathExp::parse("2+3").unwrap();
+    m.calculate();
+    m.push_str("*2").unwrap();
+    m.calculate();
+    let h = m.history();
+    assert_eq!(h.len(), 2);
+    // Предыдущий результат записывается значением.
+    let format = NumberFormat::default().exact();
+    assert_eq!(h.get(1).unwrap().expression(Language::En, &format), "5*2");
+    assert_eq!(h.get(1).unwrap().result, 10.0);
+}
+
+#[test]
+fn expression_follows_language() {
+    let mut m = MathExp::parse("tg(0)+lg(1)").unwrap();
+    m.calculate();
+    let entry = m.history().last().unwrap();
+    let format = NumberFormat::default().exact();
+    assert_eq!(entry.expression(Language::Ru, &format), "tg(0)+lg(1)");
+    assert_eq!(entry.expression(Language::En, &format), "tan(0)+log10(1)");
+}
@@ -0,0 +1,148 @@
+use calculator_wasm_rust_pwa::format::{DecimalSeparator, Grouping, NumberFormat};
+use calculator_wasm_rust_pwa::i18n::Language;
+use calculator_wasm_rust_pwa::math_exp::MathExp;
+
+const INPUTS: [&str; 24] = [
+    "0", "7", ".", ",", ";", "+", "-", "*", "/", "^", "(", ")", "=", "√", "sin", "max", "pi", "e", "Ans", "x",
+    "12", "1.5", "#", "",
+];
+
+const STATES: [&str; 16] = [
+    "", "1", "1.", "0.5", "1+", "(", "(1", "(1+2)", "pi", "x", "x=", "max(1", "max(1,", "√(", "-", "2^",
+];
+
+/// Выражение в состоянии `state`: после ввода строки, после вычисления или с десятичной запятой.
+fn state(s: &str, calculated: bool, comma: bool) -> MathExp {
+    let mut m = MathExp::new();
+    if comma {
+        m.set_number_format(NumberFormat { decimal: DecimalSeparator::Comma, ..NumberFormat::default() });
+    }
+    m.push_str(s).unwrap();
+    if calculated { m.calculate(); }
+    m
+}
+
+#[test]
+fn can_accept_matches_add() {
+    for calculated in [false, true] {
+        for comma in [false, true] {
+            for s in STATES {
+                for input in INPUTS {
+                    let expected = state(s, calculated, comma).can_accept(input);
+                    let mut m = state(s, calculated, comma);
+                    assert_eq!(
+                        m.add(input),
+                        expected,
+                        "add({:?}) после {:?} (вычислено: {}, запятая: {})",
+                        input, s, calculated, comma,
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn can_accept_does_not_change_expression() {
+    let m = state("1+2", true, false);
+    let before = (m.to_string(), m.get_output());
+    assert!(m.can_accept("+"));
+    assert!(m.can_accept("5"));
+    assert_eq!((m.to_string(), m.get_output()), before);
+}
+
+fn comma() -> MathExp {
+    let mut m = MathExp::new();
+    m.set_number_format(NumberFormat::for_language(Language::Ru));
+    m
+}
+
+#[test]
+fn decimal_comma() {
+    let mut m = comma();
+    m.push_str("1,5*2").unwrap();
+    assert_eq!(m.evaluate(), Ok(3.0));
+    assert_eq!(m.copy_text(), "1,5*2");
+    // Точка при вводе также остается десятичным разделителем.
+    let mut m = comma();
+    m.push_str("0.25+1,25").unwrap();
+    assert_eq!(m.evaluate(), Ok(1.5));
+}
+
+#[test]
+fn semicolon_separates_arguments() {
+    let mut m = comma();
+    m.push_str("max(1,5; 2; 0,5)").unwrap();
+    assert_eq!(m.evaluate(), Ok(2.0));
+    assert_eq!(m.copy_text(), "max(1,5;2;0,5)");
+    // С десятичной точкой аргументы разделяются запятой.
+    assert_eq!(MathExp::parse("max(1.5, 2)").unwrap().evaluate(), Ok(2.0));
+}
+
+#[test]
+fn ambiguous_comma_is_rejected() {
+    let mut m = comma();
+    m.push_str("max(1;2)").unwrap();
+    assert!(!m.add(","));
+    assert!(m.get_output().contains("«;»"), "{}", m.get_output());
+    assert_eq!(m.to_string(), "max(1;2)");
+    assert!(comma().push_str("max(1,5,3)").is_err());
+}
+
+#[test]
+fn paste_grouped_numbers() {
+    // Результат на экране можно вставить обратно.
+    let mut m = comma();
+    let shown = m.number_format().format(1234.5);
+    assert_eq!(shown, "1\u{a0}234,5");
+    m.paste(&format!("{}+1", shown)).unwrap();
+    assert_eq!(m.evaluate(), Ok(1235.5));
+
+    let mut m = comma();
+    m.paste("1 234,5+1").unwrap();
+    assert_eq!(m.evaluate(), Ok(1235.5));
+    assert_eq!(MathExp::parse("1'234'567*2").unwrap().evaluate(), Ok(2469134.0));
+    // Пробел разделяет разряды, только если выбрано разделение пробелом.
+    assert!(MathExp::parse("1 234").is_err());
+    // Группа из другого числа цифр не считается разрядами.
+    assert!(comma().push_str("1 23").is_err());
+    assert!(comma().push_str("1,5 000").is_err());
+}
+
+#[test]
+fn paste_back_every_format() {
+    for decimal in [DecimalSeparator::Point, DecimalSeparator::Comma] {
+        for grouping in Grouping::ALL {
+            let format = NumberFormat { decimal, grouping, ..NumberFormat::default() };
+            for val in [1234567.5, -1234.25, 999.0, 1000.0, 0.125] {
+                let shown = format.format(val);
+                let mut m = MathExp::new();
+                m.set_number_format(format);
+                m.paste(&format!("({})*2", shown)).unwrap_or_else(|e| panic!("{:?} {:?}: {}", decimal, grouping, e.fragment));
+                assert_eq!(m.evaluate(), Ok(val * 2.0), "{:?} {:?}: {}", decimal, grouping, shown);
+            }
+        }
+    }
+}
+
+#[test]
+fn point_and_comma_grouping() {
+    let grouped = |decimal| {
+        let mut m = MathExp::new();
+        m.set_number_format(NumberFormat { decimal, grouping: Grouping::Comma, ..NumberFormat::default() });
+        m
+    };
+    // При десятичной запятой точка перед тремя цифрами разделяет разряды,
+    // а в остальных случаях остается десятичным разделителем.
+    let mut m = grouped(DecimalSeparator::Comma);
+    m.paste("1.234.567,5+1.5+0.250").unwrap();
+    assert_eq!(m.evaluate(), Ok(1234569.25));
+    let mut m = grouped(DecimalSeparator::Comma);
+    m.paste("1234.567").unwrap();
+    assert_eq!(m.evaluate(), Ok(1234.567));
+    // Запятая разделяет разряды вне вызова функции, а внутри него - аргументы.
+    let mut m = grouped(DecimalSeparator::Point);
+    m.paste("1,234.5+max(1,234)").unwrap();
+    assert_eq!(m.evaluate(), Ok(1468.5));
+    assert!(grouped(DecimalSeparator::Point).push_str("1,23").is_err());
+}
@@ -0,0 +1,96 @@
+use calculator_wasm_rust_pwa::layout::{Key, KeyAction, KeyboardLayout, LayoutError};
+
+const BASIC: &str = include_str!("../src/layouts/basic.toml");
+const SCIENTIFIC: &str = include_str!("../src/layouts/scientific.json");
+
+fn key(label: &str, span: usize) -> Key {
+    Key { label: label.to_string(), action: None, span }
+}
+
+fn layout(columns: usize, rows: Vec<Vec<Key>>) -> KeyboardLayout {
+    KeyboardLayout { name: "test".to_string(), title: String::new(), columns, rows, landscape: None }
+}
+
+#[test]
+fn bundled_layouts() {
+    let basic = KeyboardLayout::from_toml(BASIC).unwrap();
+    assert_eq!(basic, KeyboardLayout::basic());
+    assert_eq!(basic.name, "basic");
+    assert_eq!(basic.columns, 4);
+    assert_eq!(basic.landscape.as_deref(), Some("scientific"));
+
+    let scientific = KeyboardLayout::from_json(SCIENTIFIC).unwrap();
+    assert_eq!(scientific, KeyboardLayout::scientific());
+    assert_eq!(scientific.name, "scientific");
+    assert_eq!(scientific.rows[0][0].action(), KeyAction::MemoryClear(0));
+
+    let names: Vec<String> = KeyboardLayout::builtin().into_iter().map(|l| l.name).collect();
+    assert_eq!(names, ["basic", "scientific"]);
+    assert_eq!(KeyboardLayout::default(), KeyboardLayout::builtin()[0]);
+}
+
+#[test]
+fn key_defaults() {
+    let layout = KeyboardLayout::from_toml(BASIC).unwrap();
+    let seven = &layout.rows[1][0];
+    assert_eq!(seven.span, 1);
+    assert_eq!(seven.action(), KeyAction::Insert("7".to_string()));
+    let zero = &layout.rows[4][0];
+    assert_eq!(zero.span, 2);
+    assert_eq!(layout.rows[0][0].action(), KeyAction::Clear);
+}
+
+#[test]
+fn format_detection() {
+    assert_eq!(KeyboardLayout::parse(BASIC).unwrap(), KeyboardLayout::basic());
+    assert_eq!(KeyboardLayout::parse(&format!("\n  {}", SCIENTIFIC)).unwrap(), KeyboardLayout::scientific());
+    assert_eq!(KeyboardLayout::from_file("basic.toml", BASIC).unwrap(), KeyboardLayout::basic());
+    assert_eq!(KeyboardLayout::from_file("dir/scientific.json", SCIENTIFIC).unwrap(), KeyboardLayout::scientific());
+    assert!(matches!(KeyboardLayout::from_file("basic.json", BASIC), Err(LayoutError::Parse(_))));
+    assert_eq!(KeyboardLayout::from_file("basic.txt", BASIC), Err(LayoutError::UnknownFormat("txt".to_string())));
+    assert_eq!(KeyboardLayout::from_file("basic", BASIC), Err(LayoutError::UnknownFormat(String::new())));
+}
+
+#[test]
+fn parse_errors() {
+    assert!(matches!(KeyboardLayout::from_json("{\"name\": \"x\""), Err(LayoutError::Parse(_))));
+    assert!(matches!(KeyboardLayout::from_toml("name = "), Err(LayoutError::Parse(_))));
+    // Неизвестное действие клавиши.
+    let s = "name = \"x\"\ncolumns = 1\nrows = [[{ label = \"1\", action = \"explode\" }]]";
+    assert!(matches!(KeyboardLayout::from_toml(s), Err(LayoutError::Parse(_))));
+}
+
+#[test]
+fn validate() {
+    assert!(layout(2, vec![vec![key("1", 1), key("2", 1)], vec![key("0", 2)]]).validate().is_ok());
+    assert!(layout(3, vec![vec![key("1", 1)]]).validate().is_ok());
+    assert_eq!(layout(0, vec![vec![key("1", 1)]]).validate(), Err(LayoutError::Empty));
+    assert_eq!(layout(2, Vec::new()).validate(), Err(LayoutError::Empty));
+    assert_eq!(layout(2, vec![Vec::new(), Vec::new()]).validate(), Err(LayoutError::Empty));
+    assert_eq!(layout(2, vec![vec![key("1", 1)], vec![key("0", 3)]]).validate(), Err(LayoutError::InvalidRow(1)));
+    assert_eq!(layout(2, vec![vec![key("1", 0)]]).validate(), Err(LayoutError::InvalidRow(0)));
+
+    let s = "name = \"x\"\ncolumns = 2\nrows = [[{ label = \"1\" }, { label = \"2\" }, { label = \"3\" }]]";
+    assert_eq!(KeyboardLayout::from_toml(s), Err(LayoutError::InvalidRow(0)));
+    let s = r#"{"name": "x", "columns": 3, "rows": []}"#;
+    assert_eq!(KeyboardLayout::from_json(s), Err(LayoutError::Empty));
+}
+
+#[test]
+fn landscape_replacement() {
+    let layouts = KeyboardLayout::builtin();
+    let (basic, scientific) = (&layouts[0], &layouts[1]);
+    assert_eq!(basic.displayed(&layouts, false).name, "basic");
+    assert_eq!(basic.displayed(&layouts, true).name, "scientific");
+    assert_eq!(scientific.displayed(&layouts, true).name, "scientific");
+    // Если замены нет среди раскладок, показывается сама раскладка.
+    assert_eq!(basic.displayed(&layouts[..1], true).name, "basic");
+}
+
+#[test]
+fn errors_display_in_english() {
+    use calculator_wasm_rust_pwa::i18n::Language;
+
+    assert_eq!(LayoutError::InvalidRow(0).to_string(), "Layout row 1 has invalid width.");
+    assert_eq!(LayoutError::InvalidRow(0).localized(Language::Ru), "Неверная ширина строки 1 раскладки.");
+}
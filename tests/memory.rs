@@ -0,0 +1,83 @@
+use calculator_wasm_rust_pwa::math_exp::{MathExp, MEMORY_REGISTERS};
+
+mod common;
+
+use common::parse;
+
+#[test]
+fn add_and_sub_over_registers() {
+    let mut m = parse("2+3");
+    m.memory_add(0);
+    m.memory_add(0);
+    m.memory_sub(3);
+    assert_eq!(m.memory(0), 10.0);
+    assert_eq!(m.memory(3), -5.0);
+    assert_eq!(m.memory(1), 0.0);
+    assert!(m.has_memory());
+}
+
+#[test]
+fn memory_keeps_expression_and_output() {
+    let mut m = parse("2*4");
+    m.memory_store(1);
+    assert_eq!(m.memory(1), 8.0);
+    // Выражение не вычисляется и не попадает в историю.
+    assert_eq!(m.to_string(), "2*4");
+    assert_eq!(m.get_output(), "");
+    assert!(m.history().is_empty());
+    assert_eq!(m.ans(), None);
+    // Ввод числа можно продолжить.
+    assert!(m.add("5"));
+    assert_eq!(m.to_string(), "2*45");
+}
+
+#[test]
+fn empty_expression_uses_last_result() {
+    let mut m = parse("6/3");
+    m.calculate();
+    m.memory_add(2);
+    m.memory_add(2);
+    assert_eq!(m.memory(2), 4.0);
+    // Без результата и выражения память не меняется.
+    let mut m = MathExp::new();
+    m.memory_add(0);
+    assert!(!m.has_memory());
+}
+
+#[test]
+fn invalid_expression_leaves_memory() {
+    let mut m = parse("1/0");
+    m.memory_store(0);
+    assert_eq!(m.memory(0), 0.0);
+    assert_eq!(m.get_error(), None);
+    // Для присваивания берется значение правой части, переменная не создается.
+    let mut m = parse("x=7");
+    m.memory_store(4);
+    assert_eq!(m.memory(4), 7.0);
+    assert_eq!(m.env().get("x"), None);
+}
+
+#[test]
+fn recall_and_clear() {
+    let mut m = parse("1.5");
+    m.memory_store(9);
+    m.clear();
+    assert!(m.add("2"));
+    assert!(m.add("*"));
+    assert!(m.memory_recall(9));
+    assert_eq!(m.evaluate(), Ok(3.0));
+    // После числа вставить значение из памяти нельзя.
+    assert!(!m.memory_recall(9));
+    m.memory_clear(9);
+    assert_eq!(m.memory(9), 0.0);
+    assert!(!m.has_memory());
+}
+
+#[test]
+fn out_of_range_register() {
+    let mut m = parse("5");
+    m.memory_store(MEMORY_REGISTERS);
+    assert!(!m.memory_recall(MEMORY_REGISTERS));
+    assert_eq!(m.memory(MEMORY_REGISTERS), 0.0);
+    assert!(!m.has_memory());
+}
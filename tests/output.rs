@@ -0,0 +1,51 @@
+use calculator_wasm_rust_pwa::format::NumberFormat;
+use calculator_wasm_rust_pwa::math_exp::MathExp;
+
+mod common;
+
+use common::{parse, run};
+
+fn output(s: &str, format: NumberFormat) -> String {
+    let mut m = MathExp::new();
+    m.set_number_format(format);
+    run(&mut m, s)
+}
+
+#[test]
+fn rounded_results_are_marked() {
+    let format = NumberFormat::default();
+    assert_eq!(output("0.1+0.2", format), "0.3");
+    assert_eq!(output("1/3", format), "≈ 0.333333333333");
+    assert_eq!(output("x=1/4", format), "x = 0.25");
+    assert_eq!(output("x=2/3", format), "x ≈ 0.666666666667");
+}
+
+#[test]
+fn exact_results_are_not_marked() {
+    let exact = NumberFormat { significant: None, ..NumberFormat::default() };
+    assert_eq!(output("0.1+0.2", exact), "0.30000000000000004");
+    assert_eq!(output("1/3", exact), (1.0f64 / 3.0).to_string());
+}
+
+#[test]
+fn negative_zero_result() {
+    assert_eq!(output("-0*5", NumberFormat::default()), "0");
+    assert_eq!(output("-1/10^300/10^300", NumberFormat::default()), "0");
+}
+
+#[test]
+fn results_paste_back() {
+    let values = [1e20, 1.23e-9, -4.5e15, 1234.5, 1.0 / 3.0, 6.02e23, 1e-7];
+    for format in [NumberFormat::default(), NumberFormat::default().plain()] {
+        for val in values {
+            let shown = format.format(val);
+            let parsed = parse(&shown).evaluate().unwrap();
+            let (_, rounded) = format.format_rounded(val);
+            if rounded {
+                assert!(((parsed - val) / val).abs() < 1e-11, "{} -> {}", val, shown);
+            } else {
+                assert_eq!(parsed, val, "{}", shown);
+            }
+        }
+    }
+}
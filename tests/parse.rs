@@ -0,0 +1,81 @@
+use calculator_wasm_rust_pwa::error::ParseError;
+use calculator_wasm_rust_pwa::math_exp::MathExp;
+use calculator_wasm_rust_pwa::token::{Func, Op, Token};
+
+mod common;
+
+use common::{eval, parse};
+
+fn error(s: &str) -> ParseError {
+    MathExp::parse(s).err().unwrap()
+}
+
+#[test]
+fn multi_digit_numbers() {
+    let m = parse("123+4.56");
+    assert_eq!(m.tokens(), &[Token::Operand(123.0), Token::Operation(Op::Add)]);
+    assert_eq!(m.buffer(), "4.56");
+    assert_eq!(eval("123+4.56"), 127.56);
+    assert_eq!(eval(".5*4"), 2.0);
+}
+
+#[test]
+fn whitespace_is_ignored() {
+    assert_eq!(eval("  12 *\t( 3 - 1 )  "), 24.0);
+    assert_eq!(eval("sin (0) + 1"), 1.0);
+}
+
+#[test]
+fn function_names() {
+    let m = parse("√(16)");
+    assert_eq!(m.tokens()[0], Token::Function(Func::Sqrt));
+    assert_eq!(eval("√(16)+ln(1)"), 4.0);
+    assert_eq!(eval("log2(8)"), 3.0);
+    assert_eq!(eval("max(1, 5, 3)"), 5.0);
+}
+
+#[test]
+fn separated_numbers_are_rejected() {
+    assert_eq!(error("1 2"), ParseError { position: 2, fragment: "2".to_string() });
+    assert_eq!(error("12 34+1"), ParseError { position: 3, fragment: "3".to_string() });
+}
+
+#[test]
+fn error_positions() {
+    assert_eq!(error("1+*2"), ParseError { position: 2, fragment: "*".to_string() });
+    assert_eq!(error("2*#"), ParseError { position: 2, fragment: "#".to_string() });
+    assert_eq!(error("1.2.3"), ParseError { position: 3, fragment: ".".to_string() });
+    assert_eq!(error("(1+2)3"), ParseError { position: 5, fragment: "3".to_string() });
+}
+
+#[test]
+fn push_str_keeps_accepted_tokens() {
+    let mut m = MathExp::new();
+    assert!(m.push_str("1+2)").is_err());
+    assert_eq!(m.to_string(), "1+2");
+}
+
+#[test]
+fn errors_display_in_english() {
+    use calculator_wasm_rust_pwa::error::CalcError;
+    use calculator_wasm_rust_pwa::i18n::Language;
+
+    let e = error("1+*2");
+    assert_eq!(e.to_string(), "Cannot parse \"*\" at position 2");
+    assert_eq!(e.localized(Language::Ru), "Не удалось разобрать \"*\" в позиции 2");
+    assert_eq!(CalcError::DivisionByZero(1).to_string(), "Division by zero.");
+    assert_eq!(CalcError::DivisionByZero(1).localized(Language::Ru), "Деление на ноль.");
+}
+
+#[test]
+fn exponent_notation() {
+    assert_eq!(eval("1e5"), 1e5);
+    assert_eq!(eval("1.5e3+1"), 1501.0);
+    assert_eq!(eval("6.02E+23"), 6.02e23);
+    assert_eq!(eval("2.5e-3*2"), 0.005);
+    // Без цифр после `e` это константа, которая не может стоять после числа.
+    assert_eq!(error("2e"), ParseError { position: 1, fragment: "e".to_string() });
+    assert_eq!(eval("2*e"), 2.0 * std::f64::consts::E);
+    assert_eq!(error("1e999"), ParseError { position: 1, fragment: "e999".to_string() });
+    assert_eq!(error("1e3 4"), ParseError { position: 4, fragment: "4".to_string() });
+}
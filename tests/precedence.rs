@@ -0,0 +1,97 @@
+
+use calculator_wasm_rust_pwa::token::{Op, Token};
+
+mod common;
+
+use common::{display, eval, parse};
+
+#[test]
+fn left_assoc_chains() {
+    assert_eq!(eval("2-3-4+5*6-1"), 24.0);
+    assert_eq!(eval("100/10/5"), 2.0);
+    assert_eq!(eval("8/2/2*3"), 6.0);
+    assert_eq!(eval("10-2+3"), 11.0);
+    assert_eq!(eval("2*3/4*8"), 12.0);
+}
+
+#[test]
+fn exp_is_right_assoc() {
+    assert_eq!(eval("2^3^2"), 512.0);
+    assert_eq!(eval("(2^3)^2"), 64.0);
+    assert_eq!(eval("2^1^3^2"), 2.0);
+}
+
+#[test]
+fn mixed_precedence() {
+    assert_eq!(eval("2*3^2"), 18.0);
+    assert_eq!(eval("2^3*2"), 16.0);
+    assert_eq!(eval("1+2*3^2/3-4"), 3.0);
+    assert_eq!(eval("2-3*4^2/8+1"), -3.0);
+    assert_eq!(eval("(1+2)*(3-4)^2"), 3.0);
+}
+
+#[test]
+fn function_before_operation() {
+    assert_eq!(eval("√(16)/(2*2)"), 1.0);
+    assert_eq!(eval("2-sin(0)*3"), 2.0);
+    assert_eq!(eval("√(4)^3"), 8.0);
+    assert_eq!(eval("cos(0)+cos(0)*2"), 3.0);
+}
+
+#[test]
+fn expr_display_respects_assoc() {
+    assert_eq!(display("2^3^2"), "2^3^2");
+    assert_eq!(display("(2^3)^2"), "(2^3)^2");
+    assert_eq!(display("(2-3)-4"), "2-3-4");
+    assert_eq!(display("2-(3-4)"), "2-(3-4)");
+    assert_eq!(display("√(16)/(2*2)"), "√(16)/(2*2)");
+}
+
+#[test]
+fn unary_minus_and_plus() {
+    assert_eq!(eval("-(3+4)"), -7.0);
+    assert_eq!(eval("2*-sin(1)"), 2.0 * -(1f64.sin()));
+    assert_eq!(eval("--5"), 5.0);
+    assert_eq!(eval("+-+5"), -5.0);
+    assert_eq!(eval("-2^2"), -4.0);
+    assert_eq!(eval("2^-1"), 0.5);
+    assert_eq!(eval("3--2"), 5.0);
+}
+
+#[test]
+fn unary_tokens_without_synthetic_parens() {
+    let m = parse("-(3+4)*-2");
+    assert_eq!(
+        m.tokens(),
+        &[
+            Token::Operation(Op::Neg),
+            Token::Operation(Op::ParenLeft),
+            Token::Operand(3.0),
+            Token::Operation(Op::Add),
+            Token::Operand(4.0),
+            Token::Operation(Op::ParenRight),
+            Token::Operation(Op::Multi),
+            Token::Operation(Op::Neg),
+        ]
+    );
+    assert_eq!(m.buffer(), "2");
+    let m = parse("--5");
+    assert_eq!(m.tokens(), &[Token::Operation(Op::Neg), Token::Operation(Op::Neg)]);
+}
+
+#[test]
+fn unary_display_and_pop() {
+    let mut m = parse("2*-5");
+    assert_eq!(m.to_string(), "2*-5");
+    m.pop();
+    assert_eq!(m.to_string(), "2*-");
+    m.pop();
+    assert_eq!(m.to_string(), "2*");
+    m.pop();
+    m.pop();
+    assert_eq!(m.to_string(), "");
+
+    assert_eq!(display("-(3+4)"), "-(3+4)");
+    assert_eq!(display("2*-sin(1)"), "2*-sin(1)");
+    assert_eq!(display("--5"), "--5");
+}
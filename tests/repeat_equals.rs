@@ -0,0 +1,59 @@
+use calculator_wasm_rust_pwa::math_exp::MathExp;
+
+/// Нажать клавиши по очереди, "=" вычисляет выражение.
+fn press(m: &mut MathExp, keys: &[&str]) {
+    for key in keys {
+        if *key == "=" { m.calculate(); } else { m.add(key); }
+    }
+}
+
+/// Результаты, которые показываются после каждого нажатия "=".
+fn results(keys: &[&str]) -> Vec<String> {
+    let mut m = MathExp::new();
+    let mut out = vec![];
+    for key in keys {
+        press(&mut m, &[key]);
+        if *key == "=" { out.push(m.get_output()); }
+    }
+    out
+}
+
+#[test]
+fn repeats_addition() {
+    assert_eq!(results(&["5", "+", "3", "=", "=", "="]), ["8", "11", "14"]);
+}
+
+#[test]
+fn repeats_non_commutative_operations() {
+    assert_eq!(results(&["2", "0", "-", "3", "=", "=", "="]), ["17", "14", "11"]);
+    assert_eq!(results(&["8", "1", "/", "3", "=", "="]), ["27", "9"]);
+    assert_eq!(results(&["2", "^", "2", "=", "=", "="]), ["4", "16", "256"]);
+}
+
+#[test]
+fn repeats_evaluated_right_operand() {
+    assert_eq!(results(&["1", "+", "(", "2", "*", "3", ")", "=", "="]), ["7", "13"]);
+    assert_eq!(results(&["2", "*", "3", "+", "4", "=", "="]), ["10", "14"]);
+}
+
+#[test]
+fn new_operation_replaces_repeat() {
+    assert_eq!(results(&["5", "+", "3", "=", "*", "2", "=", "="]), ["8", "16", "32"]);
+}
+
+#[test]
+fn nothing_to_repeat() {
+    assert_eq!(results(&["-", "5", "=", "="]), ["-5", "-5"]);
+    let mut m = MathExp::new();
+    press(&mut m, &["="]);
+    assert!(m.get_error().is_some());
+}
+
+#[test]
+fn repeat_stops_after_editing() {
+    let mut m = MathExp::new();
+    press(&mut m, &["5", "+", "3", "="]);
+    m.clear();
+    m.calculate();
+    assert!(m.get_error().is_some());
+}
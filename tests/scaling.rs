@@ -0,0 +1,65 @@
+use calculator_wasm_rust_pwa::keyboard::{fit_font_size, CalcKeyboard};
+use calculator_wasm_rust_pwa::layout::KeyboardLayout;
+use calculator_wasm_rust_pwa::math_exp::MathExp;
+use eframe::egui;
+
+/// Нарисовать клавиатуру в окне `width`x`height` точек с масштабом `pixels_per_point`
+/// и вернуть прямоугольники клавиш в точках.
+fn key_rects(width: f32, height: f32, pixels_per_point: f32) -> Vec<egui::Rect> {
+    let ctx = egui::Context::default();
+    let mut m = MathExp::new();
+    let layout = KeyboardLayout::basic();
+    let input = egui::RawInput {
+        screen_rect: Some(egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(width, height))),
+        pixels_per_point: Some(pixels_per_point),
+        ..Default::default()
+    };
+    let output = ctx.run(input, |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let size = ui.available_size();
+            CalcKeyboard::from_buffer(&mut m).layout(&layout).size(size.x, size.y).show(ui)
+        });
+    });
+    output.shapes.into_iter()
+        .filter_map(|clipped| match clipped.1 {
+            egui::Shape::Rect(rect) => { Some(rect.rect) }
+            _ => { None }
+        })
+        .collect()
+}
+
+#[test]
+fn font_fits_width() {
+    // 10 символов по 0.6 размера шрифта в ширине 120 точек.
+    assert!((fit_font_size(120.0, 10.0, 100.0) - 20.0).abs() < 1e-4);
+    assert_eq!(fit_font_size(1200.0, 10.0, 40.0), 40.0);
+    assert_eq!(fit_font_size(60.0, 100.0, 40.0), 12.0);
+    // Пустая строка считается одним символом.
+    assert!((fit_font_size(60.0, 0.0, 200.0) - 100.0).abs() < 1e-4);
+}
+
+#[test]
+fn keys_scale_with_window() {
+    let small = key_rects(320.0, 480.0, 1.0);
+    let large = key_rects(640.0, 960.0, 1.0);
+    assert!(!small.is_empty());
+    assert_eq!(small.len(), large.len());
+    for (small, large) in small.iter().zip(&large) {
+        assert!(large.width() > small.width() * 1.5, "{:?} {:?}", small, large);
+        assert!(large.height() > small.height() * 1.5, "{:?} {:?}", small, large);
+    }
+}
+
+#[test]
+fn keys_do_not_depend_on_pixels_per_point() {
+    let normal = key_rects(360.0, 640.0, 1.0);
+    for pixels_per_point in [1.5, 2.0, 3.0] {
+        let scaled = key_rects(360.0, 640.0, pixels_per_point);
+        assert_eq!(normal.len(), scaled.len());
+        for (normal, scaled) in normal.iter().zip(&scaled) {
+            // Края прямоугольников egui выравнивает по физическим пикселям.
+            assert!((normal.min - scaled.min).length() < 1.0, "{:?} {:?}", normal, scaled);
+            assert!((normal.max - scaled.max).length() < 1.0, "{:?} {:?}", normal, scaled);
+        }
+    }
+}
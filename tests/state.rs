@@ -0,0 +1,155 @@
+use calculator_wasm_rust_pwa::format::NumberFormat;
+use calculator_wasm_rust_pwa::i18n::Language;
+use calculator_wasm_rust_pwa::math_exp::MathExp;
+use calculator_wasm_rust_pwa::state::{CalcState, STATE_VERSION, STORAGE_KEY};
+use calculator_wasm_rust_pwa::token::{AngleMode, Func, Op, Token};
+
+mod common;
+
+use common::MemoryStorage;
+
+fn storage(ron: &str) -> MemoryStorage {
+    let mut storage = MemoryStorage::default();
+    storage.0.insert(STORAGE_KEY.to_string(), ron.to_string());
+    storage
+}
+
+#[test]
+fn round_trip() {
+    let mut m = MathExp::parse("x=2").unwrap();
+    m.calculate();
+    m.push_str("sin(x)*3").unwrap();
+    m.memory_store(2);
+    m.set_angle_mode(AngleMode::Deg);
+    m.push_str("+1.5").unwrap();
+
+    let mut storage = MemoryStorage::default();
+    CalcState::capture(&m).save(&mut storage);
+    let saved = CalcState::load(&storage).unwrap();
+    assert_eq!(saved, CalcState::capture(&m));
+
+    let restored = saved.restore();
+    assert_eq!(restored.to_string(), m.to_string());
+    assert_eq!(restored.buffer(), "1.5");
+    assert_eq!(restored.memory(2), m.memory(2));
+    assert_eq!(restored.env().get("x"), Some(2.0));
+    assert_eq!(restored.angle_mode(), AngleMode::Deg);
+    assert_eq!(restored.history().len(), 1);
+}
+
+#[test]
+fn missing_fields_use_defaults() {
+    let saved = CalcState::load(&storage(&format!("(version: {}, buffer: \"12\")", STATE_VERSION))).unwrap();
+    assert!(saved.tokens.is_empty());
+    assert!(saved.history.is_empty());
+    assert_eq!(saved.format, None);
+    assert_eq!(saved.restore().to_string(), "12");
+}
+
+#[test]
+fn format_follows_language() {
+    // Пока формат не выбран, используется формат языка: для русского - десятичная запятая.
+    for lang in Language::ALL {
+        let saved = CalcState::load(&storage(&format!("(version: {}, language: Some({:?}))", STATE_VERSION, lang))).unwrap();
+        let m = saved.restore();
+        assert_eq!(m.language(), lang);
+        assert_eq!(m.number_format(), NumberFormat::for_language(lang));
+    }
+    let mut m = CalcState { language: Some(Language::Ru), ..CalcState::default() }.restore();
+    m.push_str("max(1,5; 2)").unwrap();
+    assert_eq!(m.evaluate(), Ok(2.0));
+    // Выбранный пользователем формат не меняется.
+    let saved = CalcState { language: Some(Language::Ru), format: Some(NumberFormat::default()), ..CalcState::default() };
+    assert_eq!(saved.restore().number_format(), NumberFormat::default());
+}
+
+#[test]
+fn foreign_data_is_ignored() {
+    assert!(CalcState::load(&MemoryStorage::default()).is_none());
+    assert!(CalcState::load(&storage("not ron at all")).is_none());
+    assert!(CalcState::load(&storage("(buffer: \"12\")")).is_none());
+}
+
+#[test]
+fn corrupt_fields_fall_back_to_defaults() {
+    // Поврежденное поле сбрасывается, остальные сохраняются.
+    let ron = format!("(version: {}, memory: \"x\", buffer: \"12\", angle: Deg, layout: \"basic\")", STATE_VERSION);
+    let saved = CalcState::load(&storage(&ron)).unwrap();
+    assert!(saved.memory.is_empty());
+    assert_eq!(saved.buffer, "12");
+    assert_eq!(saved.angle, AngleMode::Deg);
+    assert_eq!(saved.layout, "basic");
+
+    // Обрезанные данные: поля до места обрыва читаются.
+    let ron = format!("(version: {}, angle: Grad, memory: [1.0, 2.0], tokens: [Operand(", STATE_VERSION);
+    let saved = CalcState::load(&storage(&ron)).unwrap();
+    assert_eq!(saved.angle, AngleMode::Grad);
+    assert_eq!(saved.memory, vec![1.0, 2.0]);
+    assert!(saved.tokens.is_empty());
+}
+
+#[test]
+fn other_versions_keep_readable_fields() {
+    let mut m = MathExp::parse("2*3").unwrap();
+    m.calculate();
+    m.memory_store(1);
+    let mut storage = MemoryStorage::default();
+    CalcState::capture(&m).save(&mut storage);
+    let ron = storage.0[STORAGE_KEY].replacen(&format!("version:{}", STATE_VERSION), &format!("version:{}", STATE_VERSION + 1), 1);
+    assert_ne!(ron, storage.0[STORAGE_KEY]);
+    storage.0.insert(STORAGE_KEY.to_string(), ron);
+
+    let saved = CalcState::load(&storage).unwrap();
+    assert_eq!(saved.version, STATE_VERSION);
+    assert_eq!(saved.history, *m.history());
+    assert_eq!(saved.memory[1], 6.0);
+}
+
+#[test]
+fn invalid_expression_is_dropped() {
+    let cases = vec![
+        // Два числа подряд.
+        vec![Token::Operand(1.0), Token::Operand(2.0)],
+        // Операция в начале выражения.
+        vec![Token::Operation(Op::Multi), Token::Operand(2.0)],
+        // Функция без открывающейся скобки.
+        vec![Token::Function(Func::Sin), Token::Operand(2.0)],
+        // Лишняя закрывающаяся скобка.
+        vec![Token::Operand(2.0), Token::Operation(Op::ParenRight)],
+        // Унарный знак после операнда.
+        vec![Token::Operand(1.0), Token::Operation(Op::Neg), Token::Operand(2.0)],
+        vec![
+            Token::Operation(Op::ParenLeft),
+            Token::Operand(1.0),
+            Token::Operation(Op::ParenRight),
+            Token::Operation(Op::Pos),
+            Token::Operand(2.0),
+        ],
+        // Имя функции под видом переменной и нечисловое значение.
+        vec![Token::Variable("sin".to_string())],
+        vec![Token::Operand(f64::INFINITY)],
+    ];
+    for tokens in cases {
+        let state = CalcState { tokens: tokens.clone(), buffer: "5".to_string(), memory: vec![3.0], ..CalcState::default() };
+        let m = state.restore();
+        assert_eq!(m.to_string(), "", "{:?}", tokens);
+        // Остальное состояние восстанавливается.
+        assert_eq!(m.memory(0), 3.0);
+    }
+}
+
+#[test]
+fn valid_expression_is_replayed() {
+    let tokens = vec![
+        Token::Operation(Op::Neg),
+        Token::Function(Func::Sin),
+        Token::Operation(Op::ParenLeft),
+        Token::Operand(30.0),
+        Token::Operation(Op::ParenRight),
+        Token::Operation(Op::Multi),
+    ];
+    let state = CalcState { tokens: tokens.clone(), buffer: "2".to_string(), ..CalcState::default() };
+    let m = state.restore();
+    assert_eq!(m.tokens(), tokens.as_slice());
+    assert_eq!(m.buffer(), "2");
+}
@@ -0,0 +1,80 @@
+use calculator_wasm_rust_pwa::i18n::{self, Language};
+use calculator_wasm_rust_pwa::layout::KeyAction;
+use calculator_wasm_rust_pwa::state::CalcState;
+use calculator_wasm_rust_pwa::theme::{KeyCategory, KeyColors, Theme};
+use eframe::egui::{self, Color32};
+
+mod common;
+
+use common::MemoryStorage;
+
+fn insert(s: &str) -> KeyAction {
+    KeyAction::Insert(s.to_string())
+}
+
+#[test]
+fn preset_is_applied_to_visuals() {
+    for theme in Theme::presets() {
+        let ctx = egui::Context::default();
+        theme.apply(&ctx);
+        let visuals = ctx.style().visuals.clone();
+        assert_eq!(visuals.dark_mode, theme.dark, "{}", theme.name);
+        assert_eq!(visuals.panel_fill, theme.background);
+        assert_eq!(visuals.window_fill, theme.background);
+        assert_eq!(visuals.extreme_bg_color, theme.display);
+        assert_eq!(visuals.error_fg_color, theme.error);
+    }
+    // Контрастные границы рисуются цветом выражения.
+    let theme = Theme::high_contrast();
+    let ctx = egui::Context::default();
+    theme.apply(&ctx);
+    let visuals = ctx.style().visuals.clone();
+    assert_eq!(visuals.widgets.inactive.bg_stroke, egui::Stroke::new(1.0, theme.expression));
+    assert_eq!(visuals.widgets.hovered.bg_stroke, egui::Stroke::new(2.0, theme.result));
+}
+
+#[test]
+fn custom_palette_is_persisted() {
+    let theme = Theme {
+        name: "custom".to_string(),
+        result: Color32::from_rgb(10, 200, 30),
+        operator_keys: KeyColors { fill: Color32::from_rgba_unmultiplied(1, 2, 3, 128), text: Color32::GOLD },
+        ..Theme::light()
+    };
+    let mut storage = MemoryStorage::default();
+    CalcState { theme: theme.clone(), ..CalcState::default() }.save(&mut storage);
+    let saved = CalcState::load(&storage).unwrap();
+    assert_eq!(saved.theme, theme);
+    assert_eq!(saved.theme.title(Language::En), i18n::tr(Language::En, i18n::Msg::ThemeCustom));
+}
+
+#[test]
+fn key_categories() {
+    assert_eq!(KeyCategory::of(&insert("7")), KeyCategory::Digit);
+    assert_eq!(KeyCategory::of(&insert(".")), KeyCategory::Digit);
+    assert_eq!(KeyCategory::of(&insert("+")), KeyCategory::Operator);
+    assert_eq!(KeyCategory::of(&insert("^")), KeyCategory::Operator);
+    assert_eq!(KeyCategory::of(&insert("(")), KeyCategory::Operator);
+    assert_eq!(KeyCategory::of(&insert("sin")), KeyCategory::Function);
+    assert_eq!(KeyCategory::of(&insert("π")), KeyCategory::Function);
+    assert_eq!(KeyCategory::of(&insert("Ans")), KeyCategory::Function);
+    assert_eq!(KeyCategory::of(&KeyAction::Clear), KeyCategory::Action);
+    assert_eq!(KeyCategory::of(&KeyAction::Evaluate), KeyCategory::Action);
+    assert_eq!(KeyCategory::of(&KeyAction::MemoryRecall(0)), KeyCategory::Action);
+}
+
+#[test]
+fn key_colors_by_category() {
+    let theme = Theme::high_contrast();
+    assert_eq!(theme.key_colors(KeyCategory::Digit), theme.digit_keys);
+    assert_eq!(theme.key_colors(KeyCategory::Operator), theme.operator_keys);
+    assert_eq!(theme.key_colors(KeyCategory::Function), theme.function_keys);
+    assert_eq!(theme.key_colors(KeyCategory::Action), theme.action_keys);
+    // В каждой встроенной теме группы клавиш различаются цветом.
+    for theme in Theme::presets() {
+        let fills = [theme.digit_keys.fill, theme.operator_keys.fill, theme.function_keys.fill, theme.action_keys.fill];
+        for (i, a) in fills.iter().enumerate() {
+            assert!(fills[i + 1..].iter().all(|b| b != a), "{}", theme.name);
+        }
+    }
+}
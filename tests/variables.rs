@@ -0,0 +1,100 @@
+use calculator_wasm_rust_pwa::error::{CalcError, ParseError};
+use calculator_wasm_rust_pwa::expr::{Expr, Visitor};
+use calculator_wasm_rust_pwa::math_exp::MathExp;
+use calculator_wasm_rust_pwa::token;
+
+mod common;
+
+use common::run;
+
+#[test]
+fn assignment_and_read_back() {
+    let mut m = MathExp::new();
+    assert_eq!(run(&mut m, "x=5"), "x = 5");
+    assert_eq!(m.env().get("x"), Some(5.0));
+    assert_eq!(run(&mut m, "x*2+1"), "11");
+    assert_eq!(run(&mut m, "y=x^2"), "y = 25");
+    assert_eq!(run(&mut m, "y-x"), "20");
+    // Повторное присваивание заменяет значение.
+    assert_eq!(run(&mut m, "x=1"), "x = 1");
+    assert_eq!(run(&mut m, "x+y"), "26");
+}
+
+#[test]
+fn undefined_names() {
+    let mut m = MathExp::parse("2*lgg").unwrap();
+    assert_eq!(m.evaluate(), Err(CalcError::UndefinedVariable(2)));
+    let mut m = MathExp::new();
+    run(&mut m, "z+1");
+    assert_eq!(m.get_error(), Some(&CalcError::UndefinedVariable(0)));
+}
+
+#[test]
+fn unknown_function_is_rejected() {
+    assert_eq!(MathExp::parse("sinn(2)").err(), Some(ParseError { position: 0, fragment: "sinn".to_string() }));
+    assert_eq!(MathExp::parse("1+lgg (3)").err(), Some(ParseError { position: 2, fragment: "lgg".to_string() }));
+    assert!(MathExp::parse("sin(2)").is_ok());
+}
+
+#[test]
+fn operation_after_result_starts_with_ans() {
+    let mut m = MathExp::new();
+    assert_eq!(run(&mut m, "5+3"), "8");
+    assert!(m.add("*"));
+    assert_eq!(m.tokens()[0], token::Token::Ans);
+    assert_eq!(m.to_string(), "Ans*");
+    assert_eq!(run(&mut m, "2"), "16");
+    // В истории `Ans` записан своим значением.
+    assert_eq!(m.history().last().unwrap().tokens[0], token::Token::Operand(8.0));
+    // Число после результата начинает новое выражение.
+    assert_eq!(run(&mut m, "4"), "4");
+}
+
+#[test]
+fn explicit_ans() {
+    // До первого вычисления предыдущего результата нет.
+    assert_eq!(MathExp::parse("Ans+1").unwrap().evaluate(), Err(CalcError::UndefinedVariable(0)));
+    let mut m = MathExp::new();
+    run(&mut m, "6*7");
+    assert!(m.add("Ans"));
+    assert_eq!(run(&mut m, "/2"), "21");
+    assert_eq!(run(&mut m, "ans-Ans*2"), "-21");
+    // После вычисления `Ans` вставляется как операнд, без неявной операции.
+    run(&mut m, "10");
+    m.push_str("2*Ans").unwrap();
+    assert_eq!(m.to_string(), "2*Ans");
+    assert_eq!(m.evaluate(), Ok(20.0));
+}
+
+/// Посетитель, который записывает выражение обратно в строку.
+/// Предыдущий результат посещается реализацией по умолчанию.
+struct Printer;
+
+impl Visitor for Printer {
+    type Output = String;
+
+    fn visit_number(&mut self, val: f64) -> String { val.to_string() }
+
+    fn visit_constant(&mut self, c: &token::Const) -> String { c.to_string() }
+
+    fn visit_variable(&mut self, name: &str) -> String { name.to_string() }
+
+    fn visit_unary(&mut self, op: &token::Op, operand: &Expr) -> String {
+        format!("{}{}", op, operand.accept(self))
+    }
+
+    fn visit_binary(&mut self, op: &token::Op, left: &Expr, right: &Expr) -> String {
+        format!("({}{}{})", left.accept(self), op, right.accept(self))
+    }
+
+    fn visit_call(&mut self, func: &token::Func, args: &[Expr]) -> String {
+        let args: Vec<String> = args.iter().map(|arg| arg.accept(self)).collect();
+        format!("{}({})", func, args.join(","))
+    }
+}
+
+#[test]
+fn visitor_keeps_names() {
+    let e = MathExp::parse("x+pi*Ans-2").unwrap().to_expr().unwrap();
+    assert_eq!(e.accept(&mut Printer), "((x+(π*Ans))-2)");
+}